@@ -22,11 +22,11 @@ fn main() {
     let config = format!("pub mod config {{ pub mod libdir {{\n{}}}\n}}", config);
     let out_dir = env::var("OUT_DIR").expect("Failed to get OUT_DIR environmental variable");
     let dest_path = Path::new(&out_dir).join("config.rs");
-    let mut f =
-        File::create(&dest_path).expect(&format!("Failed to create file at `{:?}`", dest_path));
+    let mut f = File::create(&dest_path)
+        .unwrap_or_else(|_| panic!("Failed to create file at `{:?}`", dest_path));
 
     f.write_all(&config.into_bytes())
-        .expect(&format!("Failed to write to file `{:?}`", dest_path));
+        .unwrap_or_else(|_| panic!("Failed to write to file `{:?}`", dest_path));
 
     let target = env::var("TARGET").expect("Failed to get TARGET environmental variable.\nPossible values: `linux`, `freebsd`, `dragonfly`");
     if target.contains("linux") {
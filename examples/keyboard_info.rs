@@ -1,7 +1,7 @@
 use device_query::{DeviceQuery, DeviceState};
 
 fn main() {
-    let device_state = DeviceState::new();
+    let device_state = DeviceState::new().expect("Failed to create DeviceState");
     let mut prev_keys = Vec::default();
 
     loop {
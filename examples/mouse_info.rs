@@ -1,8 +1,8 @@
 use device_query::{DeviceQuery, DeviceState, MouseState};
 
 fn main() {
-    let device_state = DeviceState::new();
-    let mut prev_mouse = MouseState::from((0, 0), [false; 5]);
+    let device_state = DeviceState::new().expect("Failed to create DeviceState");
+    let mut prev_mouse = MouseState::from((0, 0), &[]);
 
     loop {
         let mouse = device_state.get_mouse();
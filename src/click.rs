@@ -0,0 +1,315 @@
+use crate::{DeviceQuery, MouseButton, MouseState, Timestamp};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// The thresholds telling clicks, multiple clicks and long presses apart.
+///
+/// `DeviceState::query_click_settings()` reads them from the desktop settings where
+/// they are available, `ClickSettings::default()` holds common values.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ClickSettings {
+    /// The longest delay between two presses counted as one multiple click
+    pub multi_click_time: Duration,
+    /// The farthest the cursor may move, in pixels along each axis, between the presses
+    /// of a multiple click and while a button is held for a click
+    pub click_distance: i32,
+    /// How long a button has to be held still to be a long press
+    pub long_press_time: Duration,
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        Self {
+            multi_click_time: Duration::from_millis(400),
+            click_distance: 5,
+            long_press_time: Duration::from_millis(800),
+        }
+    }
+}
+
+/// A gesture recognized by a ClickDetector
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClickEvent {
+    /// A button was pressed and released without moving. `count` is 1 for a single
+    /// click, 2 for a double click, 3 for a triple click and so on.
+    Click {
+        button: MouseButton,
+        count: u32,
+        coordinates: (i32, i32),
+        timestamp: Timestamp,
+    },
+    /// A button was held without moving for the long press time. Releasing it
+    /// afterwards is not a click.
+    LongPress {
+        button: MouseButton,
+        coordinates: (i32, i32),
+        timestamp: Timestamp,
+    },
+}
+
+impl ClickEvent {
+    /// Check whether the event is a double click
+    pub fn is_double_click(&self) -> bool {
+        matches!(self, ClickEvent::Click { count: 2, .. })
+    }
+
+    /// Check whether the event is a triple click
+    pub fn is_triple_click(&self) -> bool {
+        matches!(self, ClickEvent::Click { count: 3, .. })
+    }
+}
+
+/// A press in progress
+struct Press {
+    start: Instant,
+    origin: (i32, i32),
+    count: u32,
+    moved: bool,
+    long: bool,
+}
+
+/// The last click of a button, which the next press may continue
+struct LastClick {
+    start: Instant,
+    origin: (i32, i32),
+    count: u32,
+}
+
+#[derive(Default)]
+struct ButtonTracker {
+    press: Option<Press>,
+    last_click: Option<LastClick>,
+}
+
+/// Derives clicks, multiple clicks and long presses from successive MouseStates.
+///
+/// Clicks are reported when the button is released, long presses as soon as the
+/// button was held long enough, so the detector has to be updated regularly.
+/// ```rust
+/// # fn example_clicks() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{ClickDetector, ClickEvent, DeviceState};
+/// # let device_state = DeviceState::new()?;
+/// let mut clicks = ClickDetector::with_settings(device_state.query_click_settings());
+///
+/// loop {
+///     for event in clicks.poll(&device_state) {
+///         if event.is_double_click() {
+///             println!("Double click");
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct ClickDetector {
+    settings: ClickSettings,
+    buttons: BTreeMap<MouseButton, ButtonTracker>,
+}
+
+impl Default for ClickDetector {
+    fn default() -> Self {
+        Self::with_settings(ClickSettings::default())
+    }
+}
+
+impl ClickDetector {
+    /// Create a ClickDetector with the default thresholds
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a ClickDetector with the given thresholds
+    pub fn with_settings(settings: ClickSettings) -> Self {
+        Self {
+            settings,
+            buttons: Default::default(),
+        }
+    }
+
+    /// Get the thresholds in use
+    pub fn settings(&self) -> ClickSettings {
+        self.settings
+    }
+
+    /// Query the mouse and return the gestures completed since the previous update
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) -> Vec<ClickEvent> {
+        self.update(&device_state.get_mouse())
+    }
+
+    /// Return the gestures completed between the previous MouseState and this one
+    pub fn update(&mut self, mouse: &MouseState) -> Vec<ClickEvent> {
+        let mut events = Vec::new();
+        let timestamp = mouse.timestamp();
+        let now = timestamp.instant();
+        let coordinates = mouse.coordinates();
+        let settings = self.settings;
+        let near = |a: (i32, i32), b: (i32, i32)| {
+            (a.0 - b.0).abs() <= settings.click_distance
+                && (a.1 - b.1).abs() <= settings.click_distance
+        };
+
+        for button in mouse.buttons() {
+            self.buttons.entry(*button).or_default();
+        }
+
+        for (button, tracker) in self.buttons.iter_mut() {
+            let down = mouse.get_button(*button);
+
+            match (tracker.press.as_mut(), down) {
+                (None, true) => {
+                    let count = match &tracker.last_click {
+                        Some(last)
+                            if now.saturating_duration_since(last.start)
+                                <= settings.multi_click_time
+                                && near(last.origin, coordinates) =>
+                        {
+                            last.count + 1
+                        }
+                        _ => 1,
+                    };
+
+                    tracker.press = Some(Press {
+                        start: now,
+                        origin: coordinates,
+                        count,
+                        moved: false,
+                        long: false,
+                    });
+                }
+                (Some(press), true) => {
+                    press.moved |= !near(press.origin, coordinates);
+
+                    if !press.moved
+                        && !press.long
+                        && now.saturating_duration_since(press.start) >= settings.long_press_time
+                    {
+                        press.long = true;
+                        events.push(ClickEvent::LongPress {
+                            button: *button,
+                            coordinates: press.origin,
+                            timestamp,
+                        });
+                    }
+                }
+                (Some(press), false) => {
+                    tracker.last_click = if press.moved || press.long {
+                        None
+                    } else {
+                        events.push(ClickEvent::Click {
+                            button: *button,
+                            count: press.count,
+                            coordinates: press.origin,
+                            timestamp,
+                        });
+                        Some(LastClick {
+                            start: press.start,
+                            origin: press.origin,
+                            count: press.count,
+                        })
+                    };
+                    tracker.press = None;
+                }
+                (None, false) => {}
+            }
+        }
+        self.buttons
+            .retain(|_, tracker| tracker.press.is_some() || tracker.last_click.is_some());
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mouse states with only the left button, sampled at the given milliseconds
+    struct Clock(Instant);
+
+    impl Clock {
+        fn mouse(&self, millis: u64, coordinates: (i32, i32), left: bool) -> MouseState {
+            let timestamp = Timestamp::from(self.0 + Duration::from_millis(millis), None);
+            let buttons: &[MouseButton] = if left { &[MouseButton::Left] } else { &[] };
+
+            MouseState::from(coordinates, buttons).with_timestamp(timestamp)
+        }
+    }
+
+    fn counts(events: Vec<ClickEvent>) -> Vec<u32> {
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                ClickEvent::Click { count, .. } => Some(count),
+                ClickEvent::LongPress { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn multi_click_test() {
+        let clock = Clock(Instant::now());
+        let mut clicks = ClickDetector::new();
+        let mut events = Vec::new();
+
+        for (millis, left) in &[
+            (0, true),
+            (50, false),
+            (200, true),
+            (250, false),
+            (500, true),
+        ] {
+            events.extend(clicks.update(&clock.mouse(*millis, (10, 10), *left)));
+        }
+        events.extend(clicks.update(&clock.mouse(550, (12, 9), false)));
+
+        assert_eq!(counts(events.clone()), vec![1, 2, 3]);
+        assert!(events[1].is_double_click());
+        assert!(events[2].is_triple_click());
+
+        // Too late to continue the triple click
+        clicks.update(&clock.mouse(1000, (12, 9), true));
+        assert_eq!(
+            counts(clicks.update(&clock.mouse(1050, (12, 9), false))),
+            vec![1]
+        );
+
+        // Too far to continue the click
+        clicks.update(&clock.mouse(1100, (20, 9), true));
+        assert_eq!(
+            counts(clicks.update(&clock.mouse(1150, (20, 9), false))),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn moved_test() {
+        let clock = Clock(Instant::now());
+        let mut clicks = ClickDetector::new();
+
+        clicks.update(&clock.mouse(0, (10, 10), true));
+        clicks.update(&clock.mouse(50, (30, 10), true));
+        assert_eq!(clicks.update(&clock.mouse(100, (10, 10), false)), vec![]);
+    }
+
+    #[test]
+    fn long_press_test() {
+        let clock = Clock(Instant::now());
+        let mut clicks = ClickDetector::new();
+
+        clicks.update(&clock.mouse(0, (10, 10), true));
+        assert_eq!(clicks.update(&clock.mouse(500, (10, 10), true)), vec![]);
+
+        let events = clicks.update(&clock.mouse(800, (11, 10), true));
+        assert!(matches!(
+            events.as_slice(),
+            [ClickEvent::LongPress {
+                button: MouseButton::Left,
+                coordinates: (10, 10),
+                ..
+            }]
+        ));
+        assert_eq!(clicks.update(&clock.mouse(900, (11, 10), true)), vec![]);
+        assert_eq!(clicks.update(&clock.mouse(1000, (11, 10), false)), vec![]);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::Timestamp;
+use std::{error::Error, fmt};
+
+/// The picture of the cursor, as returned by `DeviceState::get_cursor_image()`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CursorImage {
+    size: (u32, u32),
+    hotspot: (u32, u32),
+    serial: u64,
+    name: String,
+    pixels: Vec<u32>,
+}
+
+impl CursorImage {
+    /// Get the width and height of the image in pixels
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Get the point of the image that is at the cursor coordinates, from its top left corner
+    pub fn hotspot(&self) -> (u32, u32) {
+        self.hotspot
+    }
+
+    /// Get the number identifying the cursor, which changes whenever the cursor does
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Get the name of the cursor, such as `text` or `pointer` on Linux, empty if it has none
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the pixels row by row, as ARGB with the colors premultiplied by the alpha
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Get a single pixel of the image, `None` outside of it
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.size.0 || y >= self.size.1 {
+            return None;
+        }
+
+        self.pixels
+            .get(y as usize * self.size.0 as usize + x as usize)
+            .copied()
+    }
+
+    /// Create a CursorImage from its size, hotspot, serial, name and ARGB pixels
+    pub fn from(
+        size: (u32, u32),
+        hotspot: (u32, u32),
+        serial: u64,
+        name: &str,
+        pixels: Vec<u32>,
+    ) -> Self {
+        Self {
+            size,
+            hotspot,
+            serial,
+            name: name.to_string(),
+            pixels,
+        }
+    }
+}
+
+/// A change of the cursor picture, as reported by `DeviceState::query_cursor_events()`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CursorEvent {
+    serial: u64,
+    name: String,
+    timestamp: Timestamp,
+}
+
+impl CursorEvent {
+    /// Get the serial of the new cursor, as in `CursorImage::serial()`
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Get the name of the new cursor, empty if it has none
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the moment the cursor changed
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Create a CursorEvent from the serial and name of the new cursor and the moment it changed
+    pub fn from(serial: u64, name: &str, timestamp: Timestamp) -> Self {
+        Self {
+            serial,
+            name: name.to_string(),
+            timestamp,
+        }
+    }
+}
+
+/// An error confining the cursor with `DeviceState::confine_cursor()`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CursorError {
+    /// The display server cannot confine the cursor, on Linux for lack of the XFixes
+    /// 5.0 extension
+    Unsupported,
+    /// The region is empty or outside the desktop
+    InvalidRegion((i32, i32), (u32, u32)),
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CursorError::Unsupported => {
+                write!(f, "The display server cannot confine the cursor")
+            }
+            CursorError::InvalidRegion(position, size) => write!(
+                f,
+                "The cursor cannot be confined to {}x{} pixels at {:?}",
+                size.0, size.1, position
+            ),
+        }
+    }
+}
+
+impl Error for CursorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_test() {
+        let image = CursorImage::from((2, 2), (0, 0), 7, "default", vec![1, 2, 3, 4]);
+
+        assert_eq!(image.pixel(1, 0), Some(2));
+        assert_eq!(image.pixel(0, 1), Some(3));
+        assert_eq!(image.pixel(2, 0), None);
+        assert_eq!(image.pixel(0, 2), None);
+    }
+}
@@ -1,9 +1,25 @@
-use crate::{DeviceState, KeyCode, MouseState};
+use crate::{DeviceSnapshot, DeviceState, KeyCode, Modifiers, MouseState};
 
 /// A trait to allow the fetching of mouse and key data
 pub trait DeviceQuery {
+    /// Query the mouse position and buttons, timestamped with the moment they were sampled
     fn get_mouse(&self) -> MouseState;
+
+    /// Query the pressed keys. They carry no timestamp, use `get_state()` to also know
+    /// when they were sampled.
     fn get_keys(&self) -> Vec<KeyCode>;
+
+    /// Query for the pressed keys, mouse state and modifiers all at once.
+    ///
+    /// The default implementation combines `get_keys()` and `get_mouse()`, deriving
+    /// the held modifiers from the keys and taking the timestamp of the mouse state.
+    fn get_state(&self) -> DeviceSnapshot {
+        let keys = self.get_keys();
+        let mouse = self.get_mouse();
+        let modifiers = Modifiers::from_keys(&keys);
+        let timestamp = mouse.timestamp();
+        DeviceSnapshot::from(keys, mouse, modifiers, timestamp)
+    }
 }
 
 impl DeviceQuery for DeviceState {
@@ -13,7 +29,43 @@ impl DeviceQuery for DeviceState {
     }
 
     /// Query for all keys that are currently pressed down.
+    /// Use `get_state()` to also know when the keys were sampled.
     fn get_keys(&self) -> Vec<KeyCode> {
         self.query_keymap()
     }
+
+    /// Query for the pressed keys, mouse state and modifiers all at once.
+    fn get_state(&self) -> DeviceSnapshot {
+        self.query_state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Modifier, MouseButton};
+
+    struct FixedDevices;
+
+    impl DeviceQuery for FixedDevices {
+        fn get_mouse(&self) -> MouseState {
+            MouseState::from((3, 4), &[MouseButton::Left])
+        }
+
+        fn get_keys(&self) -> Vec<KeyCode> {
+            vec![KeyCode::LControl, KeyCode::A]
+        }
+    }
+
+    #[test]
+    fn default_get_state_test() {
+        let state = FixedDevices.get_state();
+
+        assert_eq!(state.keys(), &[KeyCode::LControl, KeyCode::A]);
+        assert_eq!(
+            state.mouse(),
+            &MouseState::from((3, 4), &[MouseButton::Left])
+        );
+        assert_eq!(state.modifiers(), Modifiers::new(&[Modifier::Control]));
+    }
 }
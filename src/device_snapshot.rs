@@ -0,0 +1,72 @@
+use crate::{timestamp::SampleTime, KeyCode, Modifiers, MouseState, Timestamp};
+
+/// The keyboard and mouse state sampled together by a single query, so that
+/// combinations like Ctrl+click are seen consistently.
+/// ```rust
+/// # fn example_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceQuery, DeviceState, DeviceSnapshot};
+/// # let device_state = DeviceState::new()?;
+/// let state: DeviceSnapshot = device_state.get_state(); // get_state() from the `DeviceQuery` trait
+/// println!("{:?} {:?} {:?}", state.keys(), state.mouse(), state.modifiers());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeviceSnapshot {
+    keys: Vec<KeyCode>,
+    mouse: MouseState,
+    modifiers: Modifiers,
+    timestamp: SampleTime,
+    device: Option<u64>,
+}
+
+impl DeviceSnapshot {
+    /// Get the keys that were pressed down
+    pub fn keys(&self) -> &[KeyCode] {
+        &self.keys
+    }
+
+    /// Get the mouse position and button state
+    pub fn mouse(&self) -> &MouseState {
+        &self.mouse
+    }
+
+    /// Get the active keyboard modifiers
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Get the moment the snapshot was taken. Its server time is always `None`, as
+    /// the state is polled.
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+
+    /// Get the id of the physical device the keys and buttons were read from, `None`
+    /// when they are those of every device
+    pub fn device(&self) -> Option<u64> {
+        self.device
+    }
+
+    /// Create a DeviceSnapshot from its parts
+    pub fn from(
+        keys: Vec<KeyCode>,
+        mouse: MouseState,
+        modifiers: Modifiers,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            keys,
+            mouse,
+            modifiers,
+            timestamp: SampleTime(timestamp),
+            device: None,
+        }
+    }
+
+    /// Set the id of the physical device the keys and buttons were read from
+    pub fn with_device(mut self, device: u64) -> Self {
+        self.device = Some(device);
+        self
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{DeviceQuery, MouseButton, MouseState, Timestamp};
+use std::collections::BTreeMap;
+
+/// A step of a drag reported by a DragTracker
+#[derive(Debug, PartialEq, Clone)]
+pub enum DragEvent {
+    /// The cursor left the dead zone around the point where the button was pressed
+    DragStart {
+        button: MouseButton,
+        origin: (i32, i32),
+        timestamp: Timestamp,
+    },
+    /// The cursor moved by `delta` since the previous event of the drag
+    DragMove {
+        button: MouseButton,
+        coordinates: (i32, i32),
+        delta: (i32, i32),
+        timestamp: Timestamp,
+    },
+    /// The button was released, ending the drag at `coordinates`
+    DragEnd {
+        button: MouseButton,
+        origin: (i32, i32),
+        coordinates: (i32, i32),
+        timestamp: Timestamp,
+    },
+}
+
+/// The drag state of a held button
+struct Hold {
+    origin: (i32, i32),
+    last: (i32, i32),
+    dragging: bool,
+}
+
+/// Derives drags from successive MouseStates, for each button separately.
+///
+/// A drag starts once the cursor moves farther than the dead zone from where the
+/// button was pressed, along either axis, which is where a `ClickDetector` with the
+/// same distance stops seeing a click. Presses that stay in the dead zone report nothing.
+/// ```rust
+/// # fn example_drags() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, DragEvent, DragTracker};
+/// # let device_state = DeviceState::new()?;
+/// let mut drags = DragTracker::new();
+///
+/// loop {
+///     for event in drags.poll(&device_state) {
+///         if let DragEvent::DragEnd { origin, coordinates, .. } = event {
+///             println!("Selected from {:?} to {:?}", origin, coordinates);
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct DragTracker {
+    dead_zone: i32,
+    holds: BTreeMap<MouseButton, Hold>,
+}
+
+impl Default for DragTracker {
+    fn default() -> Self {
+        Self::with_dead_zone(5)
+    }
+}
+
+impl DragTracker {
+    /// Create a DragTracker with a dead zone of 5 pixels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a DragTracker with the given dead zone, in pixels
+    pub fn with_dead_zone(dead_zone: i32) -> Self {
+        Self {
+            dead_zone,
+            holds: Default::default(),
+        }
+    }
+
+    /// Get the dead zone in pixels
+    pub fn dead_zone(&self) -> i32 {
+        self.dead_zone
+    }
+
+    /// Check whether a button is dragging
+    pub fn is_dragging(&self, button: MouseButton) -> bool {
+        matches!(self.holds.get(&button), Some(hold) if hold.dragging)
+    }
+
+    /// Query the mouse and return the drag events since the previous update
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) -> Vec<DragEvent> {
+        self.update(&device_state.get_mouse())
+    }
+
+    /// Return the drag events between the previous MouseState and this one
+    pub fn update(&mut self, mouse: &MouseState) -> Vec<DragEvent> {
+        let mut events = Vec::new();
+        let timestamp = mouse.timestamp();
+        let coordinates = mouse.coordinates();
+
+        for button in mouse.buttons() {
+            self.holds.entry(*button).or_insert(Hold {
+                origin: coordinates,
+                last: coordinates,
+                dragging: false,
+            });
+        }
+
+        for (button, held) in self.holds.iter_mut() {
+            if !mouse.get_button(*button) {
+                if held.dragging {
+                    events.push(DragEvent::DragEnd {
+                        button: *button,
+                        origin: held.origin,
+                        coordinates,
+                        timestamp,
+                    });
+                }
+                continue;
+            }
+
+            if !held.dragging
+                && ((coordinates.0 - held.origin.0).abs() > self.dead_zone
+                    || (coordinates.1 - held.origin.1).abs() > self.dead_zone)
+            {
+                held.dragging = true;
+                events.push(DragEvent::DragStart {
+                    button: *button,
+                    origin: held.origin,
+                    timestamp,
+                });
+            }
+
+            if held.dragging && coordinates != held.last {
+                events.push(DragEvent::DragMove {
+                    button: *button,
+                    coordinates,
+                    delta: (coordinates.0 - held.last.0, coordinates.1 - held.last.1),
+                    timestamp,
+                });
+                held.last = coordinates;
+            }
+        }
+        self.holds.retain(|button, _| mouse.get_button(*button));
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mouse(coordinates: (i32, i32), left: bool) -> MouseState {
+        let buttons: &[MouseButton] = if left { &[MouseButton::Left] } else { &[] };
+
+        MouseState::from(coordinates, buttons)
+    }
+
+    /// Strip the timestamps, which differ for every sample
+    fn steps(events: Vec<DragEvent>) -> Vec<String> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                DragEvent::DragStart { button, origin, .. } => {
+                    format!("start {:?} {:?}", button, origin)
+                }
+                DragEvent::DragMove { delta, .. } => format!("move {:?}", delta),
+                DragEvent::DragEnd {
+                    origin,
+                    coordinates,
+                    ..
+                } => format!("end {:?} {:?}", origin, coordinates),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drag_test() {
+        let mut drags = DragTracker::new();
+
+        assert!(drags.update(&mouse((10, 10), true)).is_empty());
+        assert!(drags.update(&mouse((14, 6), true)).is_empty());
+        assert!(!drags.is_dragging(MouseButton::Left));
+
+        assert_eq!(
+            steps(drags.update(&mouse((16, 10), true))),
+            vec!["start Left (10, 10)", "move (6, 0)"]
+        );
+        assert!(drags.is_dragging(MouseButton::Left));
+        assert!(drags.update(&mouse((16, 10), true)).is_empty());
+        assert_eq!(
+            steps(drags.update(&mouse((20, 30), true))),
+            vec!["move (4, 20)"]
+        );
+        assert_eq!(
+            steps(drags.update(&mouse((21, 30), false))),
+            vec!["end (10, 10) (21, 30)"]
+        );
+        assert!(!drags.is_dragging(MouseButton::Left));
+    }
+
+    #[test]
+    fn dead_zone_test() {
+        let mut drags = DragTracker::with_dead_zone(0);
+
+        drags.update(&mouse((10, 10), true));
+        assert!(drags.update(&mouse((10, 10), true)).is_empty());
+        assert!(drags.update(&mouse((10, 10), false)).is_empty());
+
+        drags.update(&mouse((10, 10), true));
+        assert_eq!(
+            steps(drags.update(&mouse((10, 11), true))),
+            vec!["start Left (10, 10)", "move (0, 1)"]
+        );
+    }
+}
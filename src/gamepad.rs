@@ -0,0 +1,189 @@
+use crate::{timestamp::SampleTime, Timestamp};
+
+/// A gamepad button, named after its position rather than its label: `South` is
+/// A on Xbox controllers and Cross on PlayStation controllers
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// The left trigger pressed past its threshold, see `GamepadState::left_trigger()`
+    /// for how far
+    LeftTrigger,
+    /// The right trigger pressed past its threshold, see `GamepadState::right_trigger()`
+    /// for how far
+    RightTrigger,
+    /// Select, Back, View or Share
+    Select,
+    /// Start, Menu or Options
+    Start,
+    /// The button with the logo of the console
+    Mode,
+    /// Pressing the left stick
+    LeftStick,
+    /// Pressing the right stick
+    RightStick,
+    /// Another button, by its Linux button code, such as the buttons of joysticks
+    Other(u16),
+}
+
+/// The state of a gamepad or a joystick, as returned by `DeviceState::query_gamepads()`.
+///
+/// Stick axes go from -1.0 to 1.0, positive to the right and downwards, and are 0.0
+/// within the dead zone around the center. Triggers go from 0.0 to 1.0.
+/// ```rust
+/// # fn example_gamepads() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, GamepadButton};
+/// # let device_state = DeviceState::new()?;
+/// for gamepad in device_state.query_gamepads() {
+///     if gamepad.is_pressed(GamepadButton::South) {
+///         println!("{} jumps, moving {:?}", gamepad.name(), gamepad.left_stick());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct GamepadState {
+    id: u64,
+    name: String,
+    buttons: Vec<GamepadButton>,
+    left_stick: (f64, f64),
+    right_stick: (f64, f64),
+    left_trigger: f64,
+    right_trigger: f64,
+    dpad: (i8, i8),
+    timestamp: SampleTime,
+}
+
+impl GamepadState {
+    /// Get the platform's identifier of the gamepad: the number N of its
+    /// `/dev/input/eventN` device on Linux, the XInput user index on Windows
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the name of the gamepad
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the held buttons, in a stable order
+    pub fn buttons(&self) -> &[GamepadButton] {
+        &self.buttons
+    }
+
+    /// Check whether a button is held
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.binary_search(&button).is_ok()
+    }
+
+    /// Get the position of the left stick
+    pub fn left_stick(&self) -> (f64, f64) {
+        self.left_stick
+    }
+
+    /// Get the position of the right stick
+    pub fn right_stick(&self) -> (f64, f64) {
+        self.right_stick
+    }
+
+    /// Get how far the left trigger is pressed
+    pub fn left_trigger(&self) -> f64 {
+        self.left_trigger
+    }
+
+    /// Get how far the right trigger is pressed
+    pub fn right_trigger(&self) -> f64 {
+        self.right_trigger
+    }
+
+    /// Get the direction held on the directional pad, each axis being -1, 0 or 1,
+    /// positive to the right and downwards
+    pub fn dpad(&self) -> (i8, i8) {
+        self.dpad
+    }
+
+    /// Get the moment the state was sampled
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+
+    /// Create a GamepadState from its id, name and held buttons, with centered sticks
+    /// and released triggers
+    pub fn from(id: u64, name: &str, buttons: &[GamepadButton]) -> Self {
+        let mut buttons = buttons.to_vec();
+        buttons.sort();
+        buttons.dedup();
+
+        Self {
+            id,
+            name: name.to_string(),
+            buttons,
+            left_stick: (0.0, 0.0),
+            right_stick: (0.0, 0.0),
+            left_trigger: 0.0,
+            right_trigger: 0.0,
+            dpad: (0, 0),
+            timestamp: SampleTime(Timestamp::now()),
+        }
+    }
+
+    /// Set the positions of the left and right sticks
+    pub fn with_sticks(mut self, left: (f64, f64), right: (f64, f64)) -> Self {
+        self.left_stick = left;
+        self.right_stick = right;
+        self
+    }
+
+    /// Set how far the left and right triggers are pressed
+    pub fn with_triggers(mut self, left: f64, right: f64) -> Self {
+        self.left_trigger = left;
+        self.right_trigger = right;
+        self
+    }
+
+    /// Set the direction held on the directional pad
+    pub fn with_dpad(mut self, dpad: (i8, i8)) -> Self {
+        self.dpad = dpad;
+        self
+    }
+
+    /// Set the moment the state was sampled
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = SampleTime(timestamp);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons_test() {
+        let gamepad = GamepadState::from(
+            3,
+            "Pad",
+            &[
+                GamepadButton::Start,
+                GamepadButton::South,
+                GamepadButton::Start,
+            ],
+        );
+
+        assert_eq!(
+            gamepad.buttons(),
+            &[GamepadButton::South, GamepadButton::Start]
+        );
+        assert!(gamepad.is_pressed(GamepadButton::Start));
+        assert!(!gamepad.is_pressed(GamepadButton::East));
+        assert_eq!(
+            gamepad.clone().with_triggers(0.5, 0.0),
+            GamepadState::from(3, "Pad", gamepad.buttons()).with_triggers(0.5, 0.0)
+        );
+    }
+}
@@ -0,0 +1,574 @@
+use crate::{DeviceQuery, DeviceSnapshot, KeyCode, KeySequence, Modifier, Modifiers};
+use std::{error::Error, fmt, str::FromStr};
+
+/// The modifiers that have to be held for a chord, as opposed to the lock keys
+const HELD_MODIFIERS: [Modifier; 4] = [
+    Modifier::Control,
+    Modifier::Shift,
+    Modifier::Alt,
+    Modifier::Meta,
+];
+
+/// A key chord such as `Ctrl+Shift+K`: a set of held modifiers plus the keys
+/// pressed along with them.
+///
+/// Chords are parsed from `+` separated strings. Modifiers are written as `Ctrl`
+/// (or `Control`), `Shift`, `Alt` and `Meta` (or `Super`, `Win`, `Cmd`), keys use
+/// the `KeyCode` names, all ignoring case. Single digits are accepted for `Key0`
+/// to `Key9`, `Esc` for `Escape` and `Return` for `Enter`. Modifier keys such as
+/// `LControl` are rejected, as holding them sets a modifier the chord would not list.
+/// ```rust
+/// # fn example_hotkey() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{Hotkey, KeyCode, Modifier};
+/// let hotkey: Hotkey = "Ctrl+Shift+K".parse()?;
+/// assert!(hotkey.modifiers().is_active(Modifier::Control));
+/// assert_eq!(hotkey.keys(), &[KeyCode::K]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A chord is active while exactly its modifiers are held, ignoring Caps Lock and Num
+/// Lock, and all of its keys are pressed. `Ctrl+K` is therefore not active on `Ctrl+Shift+K`.
+#[derive(Debug, Clone)]
+pub struct Hotkey {
+    modifiers: Modifiers,
+    keys: Vec<KeyCode>,
+}
+
+impl Hotkey {
+    /// Get the modifiers that have to be held
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Get the keys that have to be pressed
+    pub fn keys(&self) -> &[KeyCode] {
+        &self.keys
+    }
+
+    /// Check whether the chord is held in a snapshot
+    pub fn is_active(&self, snapshot: &DeviceSnapshot) -> bool {
+        let held = snapshot.modifiers();
+
+        HELD_MODIFIERS
+            .iter()
+            .all(|m| held.is_active(*m) == self.modifiers.is_active(*m))
+            && self.keys.iter().all(|k| snapshot.keys().contains(k))
+    }
+
+    /// Create a Hotkey from modifiers and keys. Lock modifiers are ignored, and
+    /// modifier keys such as `LControl` are turned into their modifier.
+    pub fn from(modifiers: Modifiers, keys: Vec<KeyCode>) -> Self {
+        let (modifier_keys, keys): (Vec<KeyCode>, Vec<KeyCode>) = keys
+            .into_iter()
+            .partition(|k| Modifier::from_key(k).is_some());
+        let held: Vec<Modifier> = HELD_MODIFIERS
+            .iter()
+            .copied()
+            .filter(|m| {
+                modifiers.is_active(*m)
+                    || modifier_keys
+                        .iter()
+                        .any(|k| Modifier::from_key(k) == Some(*m))
+            })
+            .collect();
+
+        Self {
+            modifiers: Modifiers::new(&held),
+            keys,
+        }
+    }
+}
+
+impl PartialEq for Hotkey {
+    /// Two chords are equal when they hold the same modifiers and keys, in any order
+    fn eq(&self, other: &Self) -> bool {
+        self.modifiers == other.modifiers
+            && self.keys.len() == other.keys.len()
+            && self.keys.iter().all(|k| other.keys.contains(k))
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = HotkeyError;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let (mut modifiers, mut keys) = (Vec::new(), Vec::new());
+
+        for token in chord.split('+').map(str::trim) {
+            let modifier = match token.to_ascii_lowercase().as_str() {
+                "" => return Err(HotkeyError::Parse(chord.to_string())),
+                "ctrl" | "control" => Some(Modifier::Control),
+                "shift" => Some(Modifier::Shift),
+                "alt" => Some(Modifier::Alt),
+                "meta" | "super" | "win" | "cmd" => Some(Modifier::Meta),
+                _ => None,
+            };
+
+            match modifier {
+                Some(modifier) if modifiers.contains(&modifier) => {
+                    return Err(HotkeyError::Parse(chord.to_string()))
+                }
+                Some(modifier) => modifiers.push(modifier),
+                None => {
+                    let key = parse_key(token)?;
+                    if keys.contains(&key) || Modifier::from_key(&key).is_some() {
+                        return Err(HotkeyError::Parse(chord.to_string()));
+                    }
+                    keys.push(key);
+                }
+            }
+        }
+
+        Ok(Hotkey::from(Modifiers::new(&modifiers), keys))
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tokens = Vec::new();
+
+        for (modifier, name) in HELD_MODIFIERS.iter().zip(&["Ctrl", "Shift", "Alt", "Meta"]) {
+            if self.modifiers.is_active(*modifier) {
+                tokens.push(name.to_string());
+            }
+        }
+        tokens.extend(self.keys.iter().map(|k| format!("{:?}", k)));
+
+        write!(f, "{}", tokens.join("+"))
+    }
+}
+
+/// Define `KEY_NAMES` from the list of every key, which fails to compile when a
+/// `KeyCode` variant is missing from the list
+macro_rules! key_names {
+    ($($(#[$attr:meta])* $key:ident,)*) => {
+        /// The name of every key, matching the variant names
+        const KEY_NAMES: &[(&str, KeyCode)] = &[$($(#[$attr])* (stringify!($key), KeyCode::$key),)*];
+
+        #[allow(dead_code)]
+        fn every_key_is_named(key: KeyCode) {
+            match key {
+                $($(#[$attr])* KeyCode::$key => {})*
+            }
+        }
+    };
+}
+
+key_names! {
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    #[cfg(target_os = "windows")]
+    F13,
+    #[cfg(target_os = "windows")]
+    F14,
+    #[cfg(target_os = "windows")]
+    F15,
+    #[cfg(target_os = "windows")]
+    F16,
+    #[cfg(target_os = "windows")]
+    F17,
+    #[cfg(target_os = "windows")]
+    F18,
+    #[cfg(target_os = "windows")]
+    F19,
+    #[cfg(target_os = "windows")]
+    F20,
+    #[cfg(target_os = "windows")]
+    F21,
+    #[cfg(target_os = "windows")]
+    F22,
+    #[cfg(target_os = "windows")]
+    F23,
+    #[cfg(target_os = "windows")]
+    F24,
+    Escape,
+    Space,
+    LControl,
+    RControl,
+    LShift,
+    RShift,
+    LAlt,
+    RAlt,
+    Enter,
+    #[cfg(target_os = "windows")]
+    Capital,
+    #[cfg(target_os = "windows")]
+    Delete,
+    #[cfg(target_os = "windows")]
+    Insert,
+    #[cfg(target_os = "windows")]
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    #[cfg(target_os = "windows")]
+    Numlock,
+    #[cfg(target_os = "windows")]
+    Numpad0,
+    #[cfg(target_os = "windows")]
+    Numpad1,
+    #[cfg(target_os = "windows")]
+    Numpad2,
+    #[cfg(target_os = "windows")]
+    Numpad3,
+    #[cfg(target_os = "windows")]
+    Numpad4,
+    #[cfg(target_os = "windows")]
+    Numpad5,
+    #[cfg(target_os = "windows")]
+    Numpad6,
+    #[cfg(target_os = "windows")]
+    Numpad7,
+    #[cfg(target_os = "windows")]
+    Numpad8,
+    #[cfg(target_os = "windows")]
+    Numpad9,
+    #[cfg(target_os = "windows")]
+    Add,
+    #[cfg(target_os = "windows")]
+    Decimal,
+    #[cfg(target_os = "windows")]
+    Divide,
+    #[cfg(target_os = "windows")]
+    Multiply,
+    #[cfg(target_os = "windows")]
+    Subtract,
+}
+
+impl FromStr for KeyCode {
+    type Err = String;
+
+    /// Parse a key from its variant name, ignoring case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        KEY_NAMES
+            .iter()
+            .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+            .map(|(_, key)| key.clone())
+            .ok_or_else(|| format!("Unknown key `{}`", name))
+    }
+}
+
+/// Parse a single key name, accepting the shorthands `Hotkey` documents
+pub(crate) fn parse_key(token: &str) -> Result<KeyCode, HotkeyError> {
+    let name = match token.to_ascii_lowercase().as_str() {
+        digit if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => {
+            format!("Key{}", digit)
+        }
+        "esc" => "Escape".to_string(),
+        "return" => "Enter".to_string(),
+        _ => token.to_string(),
+    };
+
+    name.parse()
+        .map_err(|_| HotkeyError::UnknownKey(token.to_string()))
+}
+
+/// The errors raised while parsing and registering hotkeys and key sequences
+#[derive(Debug, PartialEq, Clone)]
+pub enum HotkeyError {
+    /// The chord or sequence string is empty, or the chord has an empty or repeated part
+    /// or a modifier key
+    Parse(String),
+    /// A part of the chord or sequence string is neither a modifier nor a key
+    UnknownKey(String),
+    /// The chord is already registered
+    Conflict(Hotkey),
+    /// The key sequence is already registered
+    SequenceConflict(KeySequence),
+    /// The key sequence contains a cancellation key, so it could never be completed
+    CancelKey(KeySequence),
+    /// Another application holds an exclusive grab on the chord
+    AlreadyGrabbed(Hotkey),
+    /// The chord cannot be grabbed, it needs exactly one key present on the keyboard
+    Ungrabbable(Hotkey),
+}
+
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HotkeyError::Parse(chord) => write!(f, "Invalid chord `{}`", chord),
+            HotkeyError::UnknownKey(key) => write!(f, "Unknown key `{}`", key),
+            HotkeyError::Conflict(hotkey) => write!(f, "`{}` is already registered", hotkey),
+            HotkeyError::SequenceConflict(sequence) => {
+                write!(f, "`{}` is already registered", sequence)
+            }
+            HotkeyError::CancelKey(sequence) => {
+                write!(f, "`{}` contains a cancellation key", sequence)
+            }
+            HotkeyError::AlreadyGrabbed(hotkey) => {
+                write!(f, "`{}` is grabbed by another application", hotkey)
+            }
+            HotkeyError::Ungrabbable(hotkey) => write!(f, "`{}` cannot be grabbed", hotkey),
+        }
+    }
+}
+
+impl Error for HotkeyError {}
+
+/// A registered chord, its callback and whether it was active on the last update
+struct Binding {
+    hotkey: Hotkey,
+    callback: Box<dyn FnMut()>,
+    active: bool,
+}
+
+/// Calls back registered hotkeys once each time their chord is pressed.
+///
+/// Feed it with `poll()` in the same loop that would call `get_keys()`, or with
+/// `update()` if the state is already queried elsewhere.
+/// ```rust
+/// # fn example_hotkeys() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, HotkeyManager};
+/// # let device_state = DeviceState::new()?;
+/// let mut hotkeys = HotkeyManager::new();
+/// hotkeys.register("Ctrl+Shift+K", || println!("Ctrl+Shift+K pressed"))?;
+///
+/// loop {
+///     hotkeys.poll(&device_state);
+/// }
+/// # }
+/// ```
+#[derive(Default)]
+pub struct HotkeyManager {
+    bindings: Vec<Binding>,
+}
+
+impl HotkeyManager {
+    /// Create a HotkeyManager without any hotkey
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a chord string and register a callback for it
+    pub fn register<F>(&mut self, chord: &str, callback: F) -> Result<Hotkey, HotkeyError>
+    where
+        F: FnMut() + 'static,
+    {
+        let hotkey: Hotkey = chord.parse()?;
+        self.register_hotkey(hotkey.clone(), callback)?;
+        Ok(hotkey)
+    }
+
+    /// Register a callback for a chord, failing if the chord already has one
+    pub fn register_hotkey<F>(&mut self, hotkey: Hotkey, callback: F) -> Result<(), HotkeyError>
+    where
+        F: FnMut() + 'static,
+    {
+        if hotkey.keys.is_empty() && hotkey.modifiers.is_empty() {
+            return Err(HotkeyError::Parse(hotkey.to_string()));
+        }
+        if self.bindings.iter().any(|b| b.hotkey == hotkey) {
+            return Err(HotkeyError::Conflict(hotkey));
+        }
+
+        self.bindings.push(Binding {
+            hotkey,
+            callback: Box::new(callback),
+            active: false,
+        });
+        Ok(())
+    }
+
+    /// Remove the callback of a chord, returning whether it was registered
+    pub fn unregister(&mut self, hotkey: &Hotkey) -> bool {
+        let count = self.bindings.len();
+        self.bindings.retain(|b| &b.hotkey != hotkey);
+        self.bindings.len() != count
+    }
+
+    /// Get the registered chords
+    pub fn hotkeys(&self) -> Vec<&Hotkey> {
+        self.bindings.iter().map(|b| &b.hotkey).collect()
+    }
+
+    /// Query the device state and call back the chords that were just pressed
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) {
+        self.update(&device_state.get_state());
+    }
+
+    /// Call back the chords that are active in the snapshot but were not on the
+    /// previous update
+    pub fn update(&mut self, snapshot: &DeviceSnapshot) {
+        for binding in &mut self.bindings {
+            let active = binding.hotkey.is_active(snapshot);
+
+            if active && !binding.active {
+                (binding.callback)();
+            }
+            binding.active = active;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MouseState, Timestamp};
+    use std::{cell::Cell, rc::Rc};
+
+    fn snapshot(keys: Vec<KeyCode>, modifiers: &[Modifier]) -> DeviceSnapshot {
+        DeviceSnapshot::from(
+            keys,
+            MouseState::from((0, 0), &[]),
+            Modifiers::new(modifiers),
+            Timestamp::now(),
+        )
+    }
+
+    #[test]
+    fn key_names_test() {
+        for (name, key) in KEY_NAMES {
+            assert_eq!(&format!("{:?}", key), name);
+            assert_eq!(name.to_lowercase().parse::<KeyCode>(), Ok(key.clone()));
+        }
+        assert!("Key10".parse::<KeyCode>().is_err());
+    }
+
+    #[test]
+    fn parse_test() {
+        let hotkey: Hotkey = "ctrl + Shift+k".parse().unwrap();
+
+        assert_eq!(
+            hotkey.modifiers(),
+            Modifiers::new(&[Modifier::Control, Modifier::Shift])
+        );
+        assert_eq!(hotkey.keys(), &[KeyCode::K]);
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+K");
+
+        assert_eq!("Alt+1".parse::<Hotkey>().unwrap().keys(), &[KeyCode::Key1]);
+        assert_eq!("Esc".parse::<Hotkey>().unwrap().keys(), &[KeyCode::Escape]);
+        assert_eq!(
+            "Shift+K".parse::<Hotkey>().unwrap(),
+            "k+SHIFT".parse::<Hotkey>().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(
+            "Ctrl+Nope".parse::<Hotkey>(),
+            Err(HotkeyError::UnknownKey("Nope".to_string()))
+        );
+        assert_eq!(
+            "Ctrl++K".parse::<Hotkey>(),
+            Err(HotkeyError::Parse("Ctrl++K".to_string()))
+        );
+        assert_eq!(
+            "Ctrl+Control+K".parse::<Hotkey>(),
+            Err(HotkeyError::Parse("Ctrl+Control+K".to_string()))
+        );
+        assert!("".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn modifier_key_test() {
+        assert_eq!(
+            "LControl+K".parse::<Hotkey>(),
+            Err(HotkeyError::Parse("LControl+K".to_string()))
+        );
+
+        let hotkey = Hotkey::from(Modifiers::default(), vec![KeyCode::RShift, KeyCode::K]);
+        assert_eq!(hotkey, "Shift+K".parse().unwrap());
+        assert!(hotkey.is_active(&snapshot(
+            vec![KeyCode::RShift, KeyCode::K],
+            &[Modifier::Shift]
+        )));
+    }
+
+    #[test]
+    fn active_test() {
+        let hotkey: Hotkey = "Ctrl+K".parse().unwrap();
+
+        assert!(hotkey.is_active(&snapshot(
+            vec![KeyCode::LControl, KeyCode::K],
+            &[Modifier::Control, Modifier::NumLock]
+        )));
+        assert!(!hotkey.is_active(&snapshot(
+            vec![KeyCode::LControl, KeyCode::LShift, KeyCode::K],
+            &[Modifier::Control, Modifier::Shift]
+        )));
+        assert!(!hotkey.is_active(&snapshot(vec![KeyCode::K], &[])));
+    }
+
+    #[test]
+    fn conflict_test() {
+        let mut hotkeys = HotkeyManager::new();
+
+        assert!(hotkeys.register("Ctrl+Shift+K", || {}).is_ok());
+        assert_eq!(
+            hotkeys.register("Shift+Ctrl+K", || {}),
+            Err(HotkeyError::Conflict("Ctrl+Shift+K".parse().unwrap()))
+        );
+        assert!(hotkeys.register("Ctrl+K", || {}).is_ok());
+        assert!(hotkeys.unregister(&"Ctrl+K".parse().unwrap()));
+        assert!(!hotkeys.unregister(&"Ctrl+K".parse().unwrap()));
+        assert_eq!(hotkeys.hotkeys().len(), 1);
+    }
+
+    #[test]
+    fn fire_once_test() {
+        let count = Rc::new(Cell::new(0));
+        let mut hotkeys = HotkeyManager::new();
+        let counter = count.clone();
+        hotkeys
+            .register("Ctrl+K", move || counter.set(counter.get() + 1))
+            .unwrap();
+
+        let held = snapshot(vec![KeyCode::LControl, KeyCode::K], &[Modifier::Control]);
+        hotkeys.update(&held);
+        hotkeys.update(&held);
+        assert_eq!(count.get(), 1);
+
+        hotkeys.update(&snapshot(vec![KeyCode::LControl], &[Modifier::Control]));
+        hotkeys.update(&held);
+        assert_eq!(count.get(), 2);
+    }
+}
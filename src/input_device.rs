@@ -0,0 +1,108 @@
+use crate::Timestamp;
+
+/// What an input device is, as far as the system tells
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DeviceKind {
+    Keyboard,
+    /// A mouse, touchpad, trackball or another device moving the cursor
+    Pointer,
+    /// A touchscreen, where touches land where they are on the screen
+    Touch,
+    /// A graphics tablet or a pen
+    Tablet,
+    Other,
+}
+
+/// An input device, as listed by `DeviceState::devices()`.
+///
+/// On Linux, X groups the physical devices, called slave devices, under master
+/// devices: a pointer and a keyboard for each cursor, which is what the other
+/// queries of DeviceState read. Floating devices are not attached to any master,
+/// so they do not move the cursor or type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InputDevice {
+    id: u64,
+    name: String,
+    kind: DeviceKind,
+    enabled: bool,
+    is_master: bool,
+    master: Option<u64>,
+}
+
+impl InputDevice {
+    /// Get the platform's identifier of the device: the XInput device id on Linux,
+    /// the raw input device handle on Windows
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the name of the device
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get what the device is
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Check whether the device is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Check whether the device is a master device that physical devices are attached to
+    pub fn is_master(&self) -> bool {
+        self.is_master
+    }
+
+    /// Get the id of the master device the device is attached to, `None` for master
+    /// and floating devices
+    pub fn master(&self) -> Option<u64> {
+        self.master
+    }
+
+    /// Create an InputDevice from its id, name, kind, whether it is enabled, whether
+    /// it is a master device and the master it is attached to
+    pub fn from(
+        id: u64,
+        name: &str,
+        kind: DeviceKind,
+        enabled: bool,
+        is_master: bool,
+        master: Option<u64>,
+    ) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            kind,
+            enabled,
+            is_master,
+            master,
+        }
+    }
+}
+
+/// An input device appearing or disappearing, as reported by
+/// `DeviceState::query_device_events()`.
+///
+/// Gamepads are reported apart from the other devices, with the ids of their
+/// GamepadStates, which do not match those of InputDevices.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DeviceEvent {
+    /// A device was plugged in or created
+    DeviceAdded {
+        device: InputDevice,
+        timestamp: Timestamp,
+    },
+    /// The device with this id was unplugged or removed
+    DeviceRemoved { id: u64, timestamp: Timestamp },
+    /// A gamepad or joystick was plugged in
+    GamepadConnected {
+        id: u64,
+        name: String,
+        timestamp: Timestamp,
+    },
+    /// The gamepad or joystick with this id was unplugged
+    GamepadDisconnected { id: u64, timestamp: Timestamp },
+}
@@ -0,0 +1,498 @@
+use crate::{hotkey::parse_key, DeviceQuery, DeviceSnapshot, HotkeyError, KeyCode};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// The modifier keys, which do not count as a step of a sequence unless one spells them out
+const MODIFIER_KEYS: [KeyCode; 6] = [
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+];
+
+/// An ordered list of keys pressed one after the other, such as `g g`.
+///
+/// Sequences are parsed from whitespace separated key names, accepting the
+/// same names and shorthands as `Hotkey`.
+/// ```rust
+/// # fn example_sequence() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{KeyCode, KeySequence};
+/// let sequence: KeySequence = "g g".parse()?;
+/// assert_eq!(sequence.keys(), &[KeyCode::G, KeyCode::G]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeySequence {
+    keys: Vec<KeyCode>,
+}
+
+impl KeySequence {
+    /// Get the keys in the order they have to be pressed
+    pub fn keys(&self) -> &[KeyCode] {
+        &self.keys
+    }
+
+    /// Create a KeySequence from keys in the order they have to be pressed
+    pub fn from(keys: Vec<KeyCode>) -> Self {
+        Self { keys }
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = HotkeyError;
+
+    fn from_str(sequence: &str) -> Result<Self, Self::Err> {
+        let keys = sequence
+            .split_whitespace()
+            .map(parse_key)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if keys.is_empty() {
+            return Err(HotkeyError::Parse(sequence.to_string()));
+        }
+        Ok(KeySequence::from(keys))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self.keys.iter().map(|k| format!("{:?}", k)).collect();
+
+        write!(f, "{}", keys.join(" "))
+    }
+}
+
+/// How the keys typed so far relate to the registered sequences
+enum Progress {
+    /// The keys start at least one longer sequence
+    Prefix,
+    /// The keys are exactly the sequence at this index and start no other
+    Complete(usize),
+    /// No sequence starts with the keys
+    Mismatch,
+}
+
+/// A registered sequence and its callback
+struct SequenceBinding {
+    sequence: KeySequence,
+    callback: Box<dyn FnMut()>,
+}
+
+/// Calls back registered key sequences once their last key is pressed.
+///
+/// The keys of a sequence have to follow each other within the timeout, one second by
+/// default. When a sequence is also the start of a longer one, like `g` and `g g`, the
+/// recognizer waits for the next key and only calls back the shorter sequence when the
+/// timeout expires or a key that does not continue the longer one is pressed. Pressing a
+/// cancellation key, `Escape` by default, drops the keys typed so far.
+///
+/// Presses of Control, Shift and Alt are ignored unless a registered sequence contains them.
+/// ```rust
+/// # fn example_sequences() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, SequenceRecognizer};
+/// # use std::time::Duration;
+/// # let device_state = DeviceState::new()?;
+/// let mut sequences = SequenceRecognizer::new().with_timeout(Duration::from_millis(500));
+/// sequences.register("g g", || println!("Go to the top"))?;
+/// sequences.register("Up Up Down Down Left Right Left Right B A", || println!("Cheater"))?;
+///
+/// loop {
+///     sequences.poll(&device_state);
+/// }
+/// # }
+/// ```
+pub struct SequenceRecognizer {
+    bindings: Vec<SequenceBinding>,
+    timeout: Duration,
+    cancel_keys: Vec<KeyCode>,
+    held: Vec<KeyCode>,
+    typed: Vec<KeyCode>,
+    last_press: Option<Instant>,
+}
+
+impl Default for SequenceRecognizer {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+            timeout: Duration::from_secs(1),
+            cancel_keys: vec![KeyCode::Escape],
+            held: Vec::new(),
+            typed: Vec::new(),
+            last_press: None,
+        }
+    }
+}
+
+impl SequenceRecognizer {
+    /// Create a SequenceRecognizer without any sequence
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the longest delay allowed between two keys of a sequence
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the keys dropping the keys typed so far, failing if a registered sequence
+    /// contains one of them
+    pub fn with_cancel_keys(mut self, cancel_keys: Vec<KeyCode>) -> Result<Self, HotkeyError> {
+        if let Some(binding) = self
+            .bindings
+            .iter()
+            .find(|b| b.sequence.keys.iter().any(|k| cancel_keys.contains(k)))
+        {
+            return Err(HotkeyError::CancelKey(binding.sequence.clone()));
+        }
+
+        self.cancel_keys = cancel_keys;
+        Ok(self)
+    }
+
+    /// Parse a sequence string and register a callback for it
+    pub fn register<F>(&mut self, sequence: &str, callback: F) -> Result<KeySequence, HotkeyError>
+    where
+        F: FnMut() + 'static,
+    {
+        let sequence: KeySequence = sequence.parse()?;
+        self.register_sequence(sequence.clone(), callback)?;
+        Ok(sequence)
+    }
+
+    /// Register a callback for a sequence, failing if the sequence already has one or
+    /// contains a cancellation key
+    pub fn register_sequence<F>(
+        &mut self,
+        sequence: KeySequence,
+        callback: F,
+    ) -> Result<(), HotkeyError>
+    where
+        F: FnMut() + 'static,
+    {
+        if sequence.keys.is_empty() {
+            return Err(HotkeyError::Parse(sequence.to_string()));
+        }
+        if sequence.keys.iter().any(|k| self.cancel_keys.contains(k)) {
+            return Err(HotkeyError::CancelKey(sequence));
+        }
+        if self.bindings.iter().any(|b| b.sequence == sequence) {
+            return Err(HotkeyError::SequenceConflict(sequence));
+        }
+
+        self.bindings.push(SequenceBinding {
+            sequence,
+            callback: Box::new(callback),
+        });
+        self.cancel();
+        Ok(())
+    }
+
+    /// Remove the callback of a sequence, returning whether it was registered
+    pub fn unregister(&mut self, sequence: &KeySequence) -> bool {
+        let count = self.bindings.len();
+        self.bindings.retain(|b| &b.sequence != sequence);
+        self.cancel();
+        self.bindings.len() != count
+    }
+
+    /// Get the registered sequences
+    pub fn sequences(&self) -> Vec<&KeySequence> {
+        self.bindings.iter().map(|b| &b.sequence).collect()
+    }
+
+    /// Get the keys typed so far that start a registered sequence
+    pub fn pending(&self) -> &[KeyCode] {
+        &self.typed
+    }
+
+    /// Drop the keys typed so far without calling anything back
+    pub fn cancel(&mut self) {
+        self.typed.clear();
+        self.last_press = None;
+    }
+
+    /// Query the device state and call back the sequences that were just completed
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) {
+        self.update(&device_state.get_state());
+    }
+
+    /// Feed the keys pressed since the previous update to the recognizer, calling
+    /// back the sequences they complete
+    pub fn update(&mut self, snapshot: &DeviceSnapshot) {
+        let now = snapshot.timestamp().instant();
+
+        if let Some(last_press) = self.last_press {
+            if now.saturating_duration_since(last_press) > self.timeout {
+                self.expire();
+            }
+        }
+
+        let pressed: Vec<KeyCode> = snapshot
+            .keys()
+            .iter()
+            .filter(|k| !self.held.contains(k))
+            .cloned()
+            .collect();
+        self.held = snapshot.keys().to_vec();
+
+        for key in pressed {
+            self.press(key, now);
+        }
+    }
+
+    /// Handle a single key press
+    fn press(&mut self, key: KeyCode, now: Instant) {
+        if self.cancel_keys.contains(&key) {
+            self.cancel();
+            return;
+        }
+        if MODIFIER_KEYS.contains(&key)
+            && !self.bindings.iter().any(|b| b.sequence.keys.contains(&key))
+        {
+            return;
+        }
+
+        self.last_press = Some(now);
+        self.typed.push(key);
+
+        loop {
+            match self.progress(&self.typed) {
+                Progress::Prefix => return,
+                Progress::Complete(ix) => {
+                    self.typed.clear();
+                    (self.bindings[ix].callback)();
+                    return;
+                }
+                Progress::Mismatch => {
+                    let last = self.typed.len() - 1;
+
+                    // The keys before this one may complete a shorter sequence that was
+                    // waiting for a longer one. Otherwise, start over from the longest run
+                    // of the latest keys that begins a sequence.
+                    if let Some(ix) = self.position(&self.typed[..last]) {
+                        self.typed.drain(..last);
+                        (self.bindings[ix].callback)();
+                    } else {
+                        match (1..self.typed.len()).find(|start| {
+                            !matches!(self.progress(&self.typed[*start..]), Progress::Mismatch)
+                        }) {
+                            Some(start) => {
+                                self.typed.drain(..start);
+                            }
+                            None => {
+                                self.cancel();
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call back the sequence the typed keys complete, if any, once the timeout expired
+    fn expire(&mut self) {
+        if let Some(ix) = self.position(&self.typed) {
+            (self.bindings[ix].callback)();
+        }
+        self.cancel();
+    }
+
+    /// Get the index of the sequence made of exactly these keys
+    fn position(&self, keys: &[KeyCode]) -> Option<usize> {
+        self.bindings.iter().position(|b| b.sequence.keys == keys)
+    }
+
+    /// Get how far the keys go into the registered sequences
+    fn progress(&self, keys: &[KeyCode]) -> Progress {
+        if keys.is_empty() {
+            return Progress::Mismatch;
+        }
+
+        let longer = self
+            .bindings
+            .iter()
+            .any(|b| b.sequence.keys.len() > keys.len() && b.sequence.keys.starts_with(keys));
+
+        match self.position(keys) {
+            _ if longer => Progress::Prefix,
+            Some(ix) => Progress::Complete(ix),
+            None => Progress::Mismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Modifiers, MouseState, Timestamp};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Feeds key presses to a recognizer, releasing every key in between
+    struct Typist {
+        recognizer: SequenceRecognizer,
+        fired: Rc<RefCell<Vec<String>>>,
+        now: Instant,
+    }
+
+    impl Typist {
+        fn new(sequences: &[&str]) -> Self {
+            let fired = Rc::new(RefCell::new(Vec::new()));
+            let mut recognizer = SequenceRecognizer::new();
+
+            for sequence in sequences {
+                let (fired, name) = (fired.clone(), sequence.to_string());
+                recognizer
+                    .register(sequence, move || fired.borrow_mut().push(name.clone()))
+                    .unwrap();
+            }
+
+            Self {
+                recognizer,
+                fired,
+                now: Instant::now(),
+            }
+        }
+
+        fn wait(&mut self, millis: u64) {
+            self.now += Duration::from_millis(millis);
+            self.send(Vec::new());
+        }
+
+        fn send(&mut self, keys: Vec<KeyCode>) {
+            self.recognizer.update(&DeviceSnapshot::from(
+                keys,
+                MouseState::from((0, 0), &[]),
+                Modifiers::default(),
+                Timestamp::from(self.now, None),
+            ));
+        }
+
+        fn type_keys(&mut self, keys: &str) -> Vec<String> {
+            for key in keys.split_whitespace() {
+                self.send(vec![parse_key(key).unwrap()]);
+                self.send(Vec::new());
+            }
+            self.fired.borrow_mut().drain(..).collect()
+        }
+    }
+
+    #[test]
+    fn parse_test() {
+        let sequence: KeySequence = " g  G esc ".parse().unwrap();
+
+        assert_eq!(sequence.keys(), &[KeyCode::G, KeyCode::G, KeyCode::Escape]);
+        assert_eq!(sequence.to_string(), "G G Escape");
+        assert!("".parse::<KeySequence>().is_err());
+        assert_eq!(
+            "g Nope".parse::<KeySequence>(),
+            Err(HotkeyError::UnknownKey("Nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn sequence_test() {
+        let mut typist = Typist::new(&["g g"]);
+
+        assert_eq!(typist.type_keys("g"), Vec::<String>::new());
+        assert_eq!(typist.recognizer.pending(), &[KeyCode::G]);
+        assert_eq!(typist.type_keys("g"), vec!["g g"]);
+        assert_eq!(typist.type_keys("a g b g g"), vec!["g g"]);
+    }
+
+    #[test]
+    fn held_key_test() {
+        let mut typist = Typist::new(&["g g"]);
+
+        typist.send(vec![KeyCode::G]);
+        typist.send(vec![KeyCode::G]);
+        typist.send(vec![KeyCode::G, KeyCode::LShift]);
+        assert_eq!(typist.recognizer.pending(), &[KeyCode::G]);
+    }
+
+    #[test]
+    fn timeout_test() {
+        let mut typist = Typist::new(&["g g"]);
+
+        typist.type_keys("g");
+        typist.wait(1001);
+        assert_eq!(typist.type_keys("g"), Vec::<String>::new());
+        typist.wait(999);
+        assert_eq!(typist.type_keys("g"), vec!["g g"]);
+    }
+
+    #[test]
+    fn ambiguity_test() {
+        let mut typist = Typist::new(&["g", "g g", "d"]);
+
+        assert_eq!(typist.type_keys("g g"), vec!["g g"]);
+        assert_eq!(typist.type_keys("g"), Vec::<String>::new());
+        typist.wait(1001);
+        assert_eq!(
+            typist.fired.borrow_mut().drain(..).collect::<Vec<_>>(),
+            vec!["g"]
+        );
+        assert_eq!(typist.type_keys("g d"), vec!["g", "d"]);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mut typist = Typist::new(&["g", "g g"]);
+
+        assert_eq!(typist.type_keys("g esc g"), Vec::<String>::new());
+        assert_eq!(typist.recognizer.pending(), &[KeyCode::G]);
+    }
+
+    #[test]
+    fn overlap_test() {
+        let mut typist = Typist::new(&["Up Up Down Down Left Right Left Right B A"]);
+
+        assert_eq!(
+            typist.type_keys("Up Up Up Down Down Left Right Left Right B A"),
+            vec!["Up Up Down Down Left Right Left Right B A"]
+        );
+    }
+
+    #[test]
+    fn conflict_test() {
+        let mut sequences = SequenceRecognizer::new();
+
+        assert!(sequences.register("g g", || {}).is_ok());
+        assert_eq!(
+            sequences.register("G G", || {}),
+            Err(HotkeyError::SequenceConflict("g g".parse().unwrap()))
+        );
+        assert!(sequences.unregister(&"g g".parse().unwrap()));
+        assert!(sequences.sequences().is_empty());
+    }
+
+    #[test]
+    fn cancel_key_test() {
+        let mut sequences = SequenceRecognizer::new();
+
+        assert_eq!(
+            sequences.register("g esc", || {}),
+            Err(HotkeyError::CancelKey("g esc".parse().unwrap()))
+        );
+        assert!(sequences.register("g q", || {}).is_ok());
+
+        let sequences = sequences.with_cancel_keys(vec![KeyCode::Q]);
+        assert_eq!(
+            sequences.as_ref().err(),
+            Some(&HotkeyError::CancelKey("g q".parse().unwrap()))
+        );
+
+        let mut sequences = SequenceRecognizer::new()
+            .with_cancel_keys(vec![KeyCode::Q])
+            .unwrap();
+        assert!(sequences.register("g esc", || {}).is_ok());
+        assert!(sequences.register("q", || {}).is_err());
+    }
+}
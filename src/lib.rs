@@ -2,11 +2,11 @@
 //! an active window. Currently works in Windows and Linux.
 //!
 //! ```rust
-//! # fn example_usage() -> Result<(), ()> {
+//! # fn example_usage() -> Result<(), Box<dyn std::error::Error>> {
 //! // A glob import will import the same things, this is just an explicit import
 //! use device_query::{DeviceQuery, DeviceState, MouseState, KeyCode};
 //!
-//! let device_state = DeviceState::new(); // DeviceState::default() is also valid  
+//! let device_state = DeviceState::new()?; // DeviceState::default() is also valid  
 //!
 //! let mouse: MouseState = device_state.get_mouse();
 //! println!("Current Mouse Coordinates: {:?}", mouse.coordinates());  
@@ -17,12 +17,66 @@
 //! # }
 //! ```
 
+mod timestamp;
+pub use timestamp::Timestamp;
+
+mod monitor;
+pub use monitor::Monitor;
+
+mod input_device;
+pub use input_device::{DeviceEvent, DeviceKind, InputDevice};
+
+mod window;
+pub use window::{FocusEvent, FocusedWindow, Window};
+
+mod cursor;
+pub use cursor::{CursorError, CursorEvent, CursorImage};
+
 mod mouse_state;
-pub use mouse_state::{MouseButton, MouseState};
+pub use mouse_state::{ButtonMapping, MouseButton, MouseState};
+
+mod scroll;
+pub use scroll::{ScrollDelta, ScrollEvent};
+
+mod motion;
+pub use motion::{MotionDelta, MotionEvent};
+
+mod gamepad;
+pub use gamepad::{GamepadButton, GamepadState};
+
+mod touch;
+pub use touch::{TouchPoint, TouchState};
+
+mod pen;
+pub use pen::{PenState, PenTool};
+
+mod modifiers;
+pub use modifiers::{Modifier, Modifiers};
+
+mod device_snapshot;
+pub use device_snapshot::DeviceSnapshot;
 
 mod device_query;
 pub use crate::device_query::DeviceQuery;
 
+mod click;
+pub use click::{ClickDetector, ClickEvent, ClickSettings};
+
+mod drag;
+pub use drag::{DragEvent, DragTracker};
+
+mod stroke;
+pub use stroke::{Stroke, StrokeRecognizer};
+
+mod motion_tracker;
+pub use motion_tracker::MotionTracker;
+
+mod hotkey;
+pub use hotkey::{Hotkey, HotkeyError, HotkeyManager};
+
+mod key_sequence;
+pub use key_sequence::{KeySequence, SequenceRecognizer};
+
 // Exposes the linux version for linux builds
 #[cfg(target_os = "linux")]
 mod linux;
@@ -1,11 +1,303 @@
-use crate::{KeyCode, MouseState};
-use std::{ptr, slice, io};
-use x11::xlib;
+use super::{
+    evdev::Gamepads,
+    property, window,
+    xcb::Xcb,
+    xerror::{self, ErrorHandler},
+    xfixes::{CursorChange, XFixes},
+    xinput::{self, RawEvents, RawTouch, ScrollValuator, TouchValuators, XInput},
+    xrandr::XRandr,
+    xsettings,
+};
+use crate::{
+    ButtonMapping, ClickSettings, CursorError, CursorEvent, CursorImage, DeviceEvent,
+    DeviceSnapshot, FocusEvent, FocusedWindow, GamepadState, Hotkey, HotkeyError, InputDevice,
+    KeyCode, Modifier, Modifiers, Monitor, MotionDelta, MotionEvent, MouseButton, MouseState,
+    PenState, ScrollEvent, Timestamp, TouchPoint, TouchState, Window,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    io, mem,
+    os::raw::{c_char, c_int, c_uint},
+    ptr, slice,
+    time::{Duration, Instant},
+};
+use x11::{xinput2, xlib};
+
+// XKB constants missing from the x11 bindings
+const XKB_MAJOR_VERSION: c_int = 1;
+const XKB_MINOR_VERSION: c_int = 0;
+const XKB_USE_CORE_KBD: u32 = 0x0100;
+
+/// How many scroll events are kept for `query_scroll_events()` before the oldest are dropped
+const MAX_SCROLL_EVENTS: usize = 256;
+
+/// How many raw motion events are kept for `query_motion_events()` before the oldest
+/// are dropped. Gaming mice report up to a thousand per second.
+const MAX_MOTION_EVENTS: usize = 1024;
+
+/// How many cursor changes are kept for `query_cursor_events()` before the oldest are dropped
+const MAX_CURSOR_EVENTS: usize = 64;
+
+/// How many device changes are kept for `query_device_events()` before the oldest are dropped
+const MAX_DEVICE_EVENTS: usize = 64;
+
+/// How many focus changes are kept for `query_focus_events()` before the oldest are dropped
+const MAX_FOCUS_EVENTS: usize = 64;
+
+/// How many failed grab renewals are kept for `query_grab_errors()` before the oldest
+/// are dropped
+const MAX_GRAB_ERRORS: usize = 64;
 
 /// The base struct for getting Mouse and Keyboard information,
 /// extra methods provided by DeviceQuery
 pub struct DeviceState {
     display: *mut xlib::Display,
+    xkb_event_base: Option<c_int>,
+    keyboard_mapping: RefCell<Option<KeyboardMapping>>,
+    /// The grabbed chords, with no key grabs for those the keyboard mapping prevents
+    grabs: RefCell<Vec<(Hotkey, Vec<KeyGrab>)>>,
+    grab_errors: RefCell<VecDeque<HotkeyError>>,
+    xinput: Option<XInput>,
+    raw_events: Cell<RawEvents>,
+    xcb: Option<Xcb>,
+    button_mapping: ButtonMapping,
+    pointer_mapping: RefCell<Option<Vec<u8>>>,
+    scroll_valuators: RefCell<Option<HashMap<c_int, Vec<ScrollValuator>>>>,
+    scroll_events: RefCell<VecDeque<ScrollEvent>>,
+    relative_devices: RefCell<Option<HashSet<c_int>>>,
+    motion_events: RefCell<VecDeque<MotionEvent>>,
+    xrandr: Option<XRandr>,
+    monitors: RefCell<Option<Vec<Monitor>>>,
+    net_active_window: xlib::Atom,
+    focused_window: Cell<Option<xlib::Window>>,
+    focus_events: RefCell<VecDeque<FocusEvent>>,
+    confine_barriers: RefCell<Vec<xlib::XID>>,
+    xfixes: Option<XFixes>,
+    cursor_hidden: Cell<bool>,
+    cursor_events: RefCell<VecDeque<CursorEvent>>,
+    device_events: RefCell<VecDeque<DeviceEvent>>,
+    touch_valuators: RefCell<Option<HashMap<c_int, TouchValuators>>>,
+    touches: RefCell<Vec<RawTouch>>,
+    gamepads: RefCell<Option<Gamepads>>,
+    _error_handler: ErrorHandler,
+}
+
+/// The reply to a pointer query
+pub(super) struct Pointer {
+    /// Relative to the root window of the screen the pointer is on
+    pub(super) coordinates: (i32, i32),
+    /// The numbers of the held buttons, after the pointer mapping
+    pub(super) buttons: Vec<u8>,
+    /// The state mask of the keyboard modifiers
+    pub(super) modifiers: c_uint,
+    /// Whether the pointer is on the default screen
+    pub(super) same_screen: bool,
+    /// The top-level window under the pointer, 0 if none
+    pub(super) child: xlib::Window,
+}
+
+/// A keycode and modifier mask passively grabbed on the root window
+type KeyGrab = (c_int, c_uint);
+
+/// A copy of the server's keycode to keysym table and of the modifier bits
+/// the keysyms are bound to, kept until the server reports a change
+struct KeyboardMapping {
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<xlib::KeySym>,
+    alt_mask: c_uint,
+    meta_mask: c_uint,
+    num_lock_mask: c_uint,
+}
+
+impl KeyboardMapping {
+    /// Fetch the keysyms of every keycode the server knows about
+    unsafe fn fetch(display: *mut xlib::Display) -> KeyboardMapping {
+        let (mut min_keycode, mut max_keycode, mut keysyms_per_keycode) = (0, 0, 0);
+        xlib::XDisplayKeycodes(display, &mut min_keycode, &mut max_keycode);
+
+        let keycode_count = max_keycode - min_keycode + 1;
+        let key_sym = xlib::XGetKeyboardMapping(
+            display,
+            min_keycode as u8,
+            keycode_count,
+            &mut keysyms_per_keycode,
+        );
+
+        let keysyms = if key_sym.is_null() {
+            Vec::new()
+        } else {
+            let len = (keycode_count * keysyms_per_keycode) as usize;
+            let keysyms = slice::from_raw_parts(key_sym, len).to_vec();
+
+            // Free the memory allocated by XGetKeyboardMapping.
+            xlib::XFree(key_sym as *mut std::ffi::c_void);
+            keysyms
+        };
+
+        let mut mapping = KeyboardMapping {
+            min_keycode: min_keycode as u8,
+            keysyms_per_keycode: keysyms_per_keycode as usize,
+            keysyms,
+            alt_mask: 0,
+            meta_mask: 0,
+            num_lock_mask: 0,
+        };
+        mapping.fetch_modifier_masks(display);
+        mapping
+    }
+
+    /// Find which of the Mod1-Mod5 bits Alt, Meta and NumLock are bound to
+    unsafe fn fetch_modifier_masks(&mut self, display: *mut xlib::Display) {
+        use x11::keysym;
+
+        let modifier_map = xlib::XGetModifierMapping(display);
+        if modifier_map.is_null() {
+            return;
+        }
+
+        let keys_per_modifier = (*modifier_map).max_keypermod as usize;
+        let keycodes = slice::from_raw_parts((*modifier_map).modifiermap, 8 * keys_per_modifier);
+
+        let (mut alt_mask, mut meta_mask, mut num_lock_mask) = (0, 0, 0);
+        for (ix, keycode) in keycodes.iter().enumerate() {
+            let mask = 1 << (ix / keys_per_modifier);
+
+            for ks in self.keysyms(*keycode) {
+                match *ks as c_uint {
+                    keysym::XK_Alt_L | keysym::XK_Alt_R => alt_mask |= mask,
+                    keysym::XK_Super_L | keysym::XK_Super_R => meta_mask |= mask,
+                    keysym::XK_Num_Lock => num_lock_mask |= mask,
+                    _ => {}
+                }
+            }
+        }
+
+        xlib::XFreeModifiermap(modifier_map);
+
+        self.alt_mask = alt_mask;
+        self.meta_mask = meta_mask;
+        self.num_lock_mask = num_lock_mask;
+    }
+
+    /// Get the keysyms bound to a keycode, empty if the keycode is unmapped
+    fn keysyms(&self, keycode: u8) -> &[xlib::KeySym] {
+        if keycode < self.min_keycode {
+            return &[];
+        }
+
+        let start = (keycode - self.min_keycode) as usize * self.keysyms_per_keycode;
+        self.keysyms
+            .get(start..start + self.keysyms_per_keycode)
+            .unwrap_or(&[])
+    }
+
+    /// Get every keycode producing a key
+    fn keycodes(&self, key: &KeyCode) -> Vec<u8> {
+        let keycode_count = self
+            .keysyms
+            .len()
+            .checked_div(self.keysyms_per_keycode)
+            .unwrap_or(0);
+
+        (0..keycode_count)
+            .map(|ix| self.min_keycode + ix as u8)
+            .filter(|keycode| {
+                self.keysyms(*keycode)
+                    .iter()
+                    .any(|ks| KeyCode::keysym_to_key(*ks as u32).as_ref() == Some(key))
+            })
+            .collect()
+    }
+
+    /// Get the key grabs needed for a chord to be consumed whatever the state of
+    /// Caps Lock and Num Lock, `None` if the chord cannot be grabbed
+    fn key_grabs(&self, hotkey: &Hotkey) -> Option<Vec<KeyGrab>> {
+        let key = match hotkey.keys() {
+            [key] => key,
+            _ => return None,
+        };
+
+        let mut mask = 0;
+        for (modifier, modifier_mask) in &[
+            (Modifier::Shift, xlib::ShiftMask),
+            (Modifier::Control, xlib::ControlMask),
+            (Modifier::Alt, self.alt_mask),
+            (Modifier::Meta, self.meta_mask),
+        ] {
+            if hotkey.modifiers().is_active(*modifier) {
+                if *modifier_mask == 0 {
+                    return None;
+                }
+                mask |= modifier_mask;
+            }
+        }
+
+        let mut lock_masks = vec![0, xlib::LockMask];
+        if self.num_lock_mask != 0 {
+            lock_masks.extend(&[self.num_lock_mask, self.num_lock_mask | xlib::LockMask]);
+        }
+
+        let grabs: Vec<KeyGrab> = self
+            .keycodes(key)
+            .into_iter()
+            .flat_map(|keycode| {
+                lock_masks
+                    .iter()
+                    .map(move |lock_mask| (keycode as c_int, mask | lock_mask))
+            })
+            .collect();
+
+        Some(grabs).filter(|grabs| !grabs.is_empty())
+    }
+
+    /// Resolve the keys set in an `XQueryKeymap` bit vector
+    fn keys(&self, key_map: &[c_char; 32]) -> Vec<KeyCode> {
+        let mut key_codes = Vec::new(); // Create vector to hold all key codes
+
+        for (ix, byte) in key_map.iter().enumerate() {
+            for bit in 0_u8..8_u8 {
+                let bitmask = 1 << bit;
+
+                if byte & bitmask != 0 {
+                    let keycode = ix as u8 * 8 + bit;
+
+                    for ks in self.keysyms(keycode) {
+                        // Attempt to match KeyCode against keys and if
+                        // the key is matched push to the key_codes vector
+                        if let Some(k) = KeyCode::keysym_to_key(*ks as u32) {
+                            key_codes.push(k)
+                        };
+                    }
+                }
+            }
+        }
+
+        // Remove consecutive duplicates from the key code vector before returning it
+        key_codes.dedup();
+        key_codes
+    }
+
+    /// Resolve the modifiers set in a core event or pointer state mask
+    fn modifiers(&self, mask: c_uint) -> Modifiers {
+        let mut modifiers = Vec::new();
+
+        for (modifier, modifier_mask) in &[
+            (Modifier::Shift, xlib::ShiftMask),
+            (Modifier::Control, xlib::ControlMask),
+            (Modifier::Alt, self.alt_mask),
+            (Modifier::Meta, self.meta_mask),
+            (Modifier::CapsLock, xlib::LockMask),
+            (Modifier::NumLock, self.num_lock_mask),
+        ] {
+            if mask & modifier_mask != 0 {
+                modifiers.push(*modifier);
+            }
+        }
+
+        Modifiers::new(&modifiers)
+    }
 }
 
 impl DeviceState {
@@ -16,109 +308,1232 @@ impl DeviceState {
             let display = xlib::XOpenDisplay(ptr::null());
 
             if display.is_null() {
-                Err(io::Error::new(io::ErrorKind::NotFound, "XOpenDisplay pointer is null"))
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "XOpenDisplay pointer is null",
+                ));
+            }
+            let error_handler = ErrorHandler::install();
+
+            // Ask XKB to tell us when the keymap changes, e.g. after `setxkbmap`.
+            // Core `MappingNotify` events are always delivered and act as a fallback.
+            let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+            let (mut major, mut minor) = (XKB_MAJOR_VERSION, XKB_MINOR_VERSION);
+            let xkb_event_base = if xlib::XkbQueryExtension(
+                display,
+                &mut opcode,
+                &mut event_base,
+                &mut error_base,
+                &mut major,
+                &mut minor,
+            ) != 0
+            {
+                let mask = xlib::XkbNewKeyboardNotifyMask | xlib::XkbMapNotifyMask;
+                xlib::XkbSelectEvents(display, XKB_USE_CORE_KBD, mask, mask);
+                Some(event_base)
             } else {
-                Ok(DeviceState { display })
+                None
+            };
+
+            // Wheel notches and side buttons are only visible through XI2, the core
+            // protocol reports the wheel as buttons 4 to 7 to the focused window alone.
+            // Its raw events are only selected by the first query needing them.
+            let root = xlib::XDefaultRootWindow(display);
+            let xinput = XInput::load(display);
+            if let Some(xinput) = &xinput {
+                xinput.select_device_events(display, root);
             }
+
+            // The window manager announces the focused window in a root window property
+            xlib::XSelectInput(display, root, xlib::PropertyChangeMask);
+
+            Ok(DeviceState {
+                display,
+                xkb_event_base,
+                keyboard_mapping: RefCell::new(None),
+                grabs: RefCell::new(Vec::new()),
+                grab_errors: RefCell::new(VecDeque::new()),
+                xinput,
+                raw_events: Cell::new(RawEvents::default()),
+                xcb: Xcb::load(display),
+                button_mapping: ButtonMapping::default(),
+                pointer_mapping: RefCell::new(None),
+                scroll_valuators: RefCell::new(None),
+                scroll_events: RefCell::new(VecDeque::new()),
+                relative_devices: RefCell::new(None),
+                motion_events: RefCell::new(VecDeque::new()),
+                xrandr: XRandr::load(display, root),
+                monitors: RefCell::new(None),
+                net_active_window: property::intern_atom(display, "_NET_ACTIVE_WINDOW"),
+                focused_window: Cell::new(window::focused_window(display, root)),
+                focus_events: RefCell::new(VecDeque::new()),
+                confine_barriers: RefCell::new(Vec::new()),
+                xfixes: XFixes::load(display, root),
+                cursor_hidden: Cell::new(false),
+                cursor_events: RefCell::new(VecDeque::new()),
+                device_events: RefCell::new(VecDeque::new()),
+                touch_valuators: RefCell::new(None),
+                touches: RefCell::new(Vec::new()),
+                gamepads: RefCell::new(None),
+                _error_handler: error_handler,
+            })
         }
     }
 
+    /// Set how the mouse buttons are named. The default `ButtonMapping::Logical` follows
+    /// the pointer mapping set with `xmodmap` or the desktop settings.
+    pub fn with_button_mapping(mut self, button_mapping: ButtonMapping) -> Self {
+        self.button_mapping = button_mapping;
+        self
+    }
+
     /// Query the mouse for it's coordinates and pressed buttons, returned as a MouseState
     pub fn query_mouse(&self) -> MouseState {
-        let (mut win_x, mut win_y, mut mask_return) = (0, 0, 0);
+        let timestamp = Timestamp::now();
+        let pointer = self.query_pointer();
+
+        self.mouse_state(&pointer, timestamp)
+    }
+
+    /// Get the wheel notches and smooth scrolling steps since the previous query, one
+    /// event each. Scrolling is followed from the first call. Requires the XInput 2.1
+    /// extension, without which nothing scrolls.
+    pub fn query_scroll_events(&self) -> Vec<ScrollEvent> {
+        self.follow_raw_events(|raw_events| raw_events.scroll = true);
+        self.process_events();
+
+        self.scroll_events.borrow_mut().drain(..).collect()
+    }
+
+    /// Get the unaccelerated movement of the mouse since the previous query, summed. It
+    /// keeps counting when the cursor is pinned against a screen edge. Movement is
+    /// reported once: by this method or `query_motion_events()`, whichever comes first,
+    /// and followed from the first call of either. Requires the XInput 2.1 extension,
+    /// without which nothing moves.
+    pub fn query_relative_motion(&self) -> MotionDelta {
+        self.follow_raw_events(|raw_events| raw_events.motion = true);
+        self.process_events();
+
+        self.motion_events
+            .borrow_mut()
+            .drain(..)
+            .fold(MotionDelta::default(), |sum, event| sum + event.delta())
+    }
+
+    /// Get the unaccelerated movements of the mouse since the previous query, one event
+    /// per report of the device. Requires the XInput 2.1 extension.
+    pub fn query_motion_events(&self) -> Vec<MotionEvent> {
+        self.follow_raw_events(|raw_events| raw_events.motion = true);
+        self.process_events();
+
+        self.motion_events.borrow_mut().drain(..).collect()
+    }
+
+    /// Query the keyboard for all pressed keys, returned as a vector of KeyCodes
+    pub fn query_keymap(&self) -> Vec<KeyCode> {
+        let key_map = self.query_raw_keymap();
+
+        self.with_keyboard_mapping(|mapping| mapping.keys(&key_map))
+    }
+
+    /// Query the keyboard and the mouse together, returned as a DeviceSnapshot. Both
+    /// requests are sent before waiting for either reply, unless libxcb is missing.
+    /// The modifiers come from the same reply as the mouse buttons.
+    pub fn query_state(&self) -> DeviceSnapshot {
+        let timestamp = Timestamp::now();
+        let (key_map, pointer) = match self.query_pipelined_state() {
+            Some(state) => state,
+            None => (self.query_raw_keymap(), self.query_pointer()),
+        };
+
+        let (keys, modifiers) = self.with_keyboard_mapping(|mapping| {
+            (mapping.keys(&key_map), mapping.modifiers(pointer.modifiers))
+        });
+        let mouse = self.mouse_state(&pointer, timestamp);
+
+        DeviceSnapshot::from(keys, mouse, modifiers, timestamp)
+    }
+
+    /// Query the fingers on the touchscreens, returned as a TouchState. The touches
+    /// are followed through XI2 events from the first call, so only the ones that
+    /// started after it are listed. Touchscreens need XI 2.2.
+    pub fn query_touches(&self) -> TouchState {
+        self.follow_raw_events(|raw_events| raw_events.touch = true);
+        self.process_events();
+
+        let touches: Vec<TouchPoint> = self
+            .touches
+            .borrow()
+            .iter()
+            .map(|touch| touch.point)
+            .collect();
+        TouchState::from(&touches)
+    }
+
+    /// Query the pens of the graphics tablets, returned as PenStates ordered by device
+    /// id. Tablet drivers usually add a device for each end of the pen, so a pen and
+    /// its eraser are listed separately.
+    pub fn query_pens(&self) -> Vec<PenState> {
+        let timestamp = Timestamp::now();
+        let xinput = match &self.xinput {
+            Some(xinput) => xinput,
+            None => return Vec::new(),
+        };
+
+        unsafe { xinput.pens(self.display, self.screen_size()) }
+            .into_iter()
+            .map(|pen| pen.with_timestamp(timestamp))
+            .collect()
+    }
+
+    /// Query the gamepads and joysticks, returned as GamepadStates ordered by id. They
+    /// are read from the event devices in `/dev/input`, which usually requires being
+    /// in the `input` group; the devices that cannot be opened are left out. Gamepads
+    /// are opened on the first call and stay open between queries, after which only
+    /// new event devices are opened.
+    pub fn query_gamepads(&self) -> Vec<GamepadState> {
+        self.update_gamepads();
+
+        self.gamepads
+            .borrow()
+            .as_ref()
+            .map_or_else(Vec::new, Gamepads::states)
+    }
+
+    /// Get the active monitors of the default screen. Without RandR 1.5, the whole
+    /// screen is reported as a single monitor.
+    pub fn query_monitors(&self) -> Vec<Monitor> {
+        self.process_events();
+
+        self.monitors
+            .borrow_mut()
+            .get_or_insert_with(|| unsafe { self.fetch_monitors() })
+            .clone()
+    }
+
+    /// Get the monitor showing a point of the default screen, `None` if the point is
+    /// between or beyond the monitors
+    pub fn monitor_at(&self, x: i32, y: i32) -> Option<Monitor> {
+        self.query_monitors()
+            .into_iter()
+            .find(|monitor| monitor.contains((x, y)))
+    }
+
+    /// Get the top-level window under the cursor, `None` over the desktop background or
+    /// on another screen. This is the application's window rather than the frame
+    /// the window manager draws around it.
+    pub fn window_under_cursor(&self) -> Option<Window> {
+        let pointer = self.query_pointer();
+        if !pointer.same_screen || pointer.child == 0 {
+            return None;
+        }
+
+        unsafe {
+            let client = window::client_window(self.display, pointer.child);
+            window::geometry(self.display, client)
+        }
+    }
+
+    /// Get where a window is, to convert desktop coordinates to coordinates relative
+    /// to it with `Window::to_local()`. `None` if the window does not exist.
+    pub fn query_window(&self, id: u64) -> Option<Window> {
+        unsafe { window::geometry(self.display, id as xlib::Window) }
+    }
+
+    /// Query the keys and buttons held on a single physical device, such as a second
+    /// keyboard used as a macro pad, returned as a DeviceSnapshot. The coordinates and
+    /// modifiers are those shared by every device. `None` if there is no physical
+    /// device with this id, which comes from `devices()`. Requires the XInput 2.1
+    /// extension.
+    pub fn query_device_state(&self, device: u64) -> Option<DeviceSnapshot> {
+        let xinput = self.xinput.as_ref()?;
+        let timestamp = Timestamp::now();
+        let held = unsafe { xinput.held_inputs(self.display, device as xlib::XID)? };
+        let pointer = self.query_pointer();
+
+        let (keys, modifiers) = self.with_keyboard_mapping(|mapping| {
+            (
+                mapping.keys(&held.keys),
+                mapping.modifiers(pointer.modifiers),
+            )
+        });
+        let mouse = MouseState::from(pointer.coordinates, &self.device_buttons(held.buttons))
+            .with_timestamp(timestamp);
+
+        Some(DeviceSnapshot::from(keys, mouse, modifiers, timestamp).with_device(device))
+    }
+
+    /// List the input devices: the master pointers and keyboards the other queries
+    /// read, and the physical devices with the master they are attached to. Requires
+    /// the XInput 2.1 extension, without which the list is empty.
+    pub fn devices(&self) -> Vec<InputDevice> {
+        match &self.xinput {
+            Some(xinput) => unsafe { xinput.devices(self.display) },
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the devices plugged in and unplugged since the previous query, oldest first,
+    /// both the physical devices and the master devices, which requires the XInput 2.1
+    /// extension, and the gamepads of `query_gamepads()`. Gamepads are followed from
+    /// the first call to either method, and the ones already plugged in then are not
+    /// reported as connected.
+    pub fn query_device_events(&self) -> Vec<DeviceEvent> {
+        self.process_events();
+        self.update_gamepads();
+
+        self.device_events.borrow_mut().drain(..).collect()
+    }
+
+    /// Get the window that has the keyboard focus with its title, class and process id,
+    /// to scope hotkeys or recordings to an application. `None` when no window has
+    /// the focus.
+    pub fn get_focused_window(&self) -> Option<FocusedWindow> {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let focused = window::focused_window(self.display, root)?;
+            window::window_info(self.display, focused)
+        }
+    }
+
+    /// Get the changes of the focused window since the previous query, oldest first.
+    /// Requires a window manager announcing the active window with `_NET_ACTIVE_WINDOW`,
+    /// which most do, without which nothing is reported.
+    pub fn query_focus_events(&self) -> Vec<FocusEvent> {
+        self.process_events();
+
+        self.focus_events.borrow_mut().drain(..).collect()
+    }
+
+    /// Move the cursor to a point of the default screen
+    pub fn set_mouse_position(&self, x: i32, y: i32) {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            xlib::XWarpPointer(self.display, 0, root, 0, 0, 0, 0, x, y);
+            xlib::XFlush(self.display);
+        }
+    }
+
+    /// Keep the cursor inside a region of the default screen until `release_cursor()`
+    /// is called or the DeviceState is dropped, moving it into the region if needed.
+    /// The region is fenced with pointer barriers, so other applications keep receiving
+    /// the pointer events. Requires the XFixes 5.0 extension.
+    pub fn confine_cursor(
+        &self,
+        position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), CursorError> {
+        self.release_cursor();
+        if size.0 == 0 || size.1 == 0 {
+            return Err(CursorError::InvalidRegion(position, size));
+        }
+        let xfixes = self.xfixes.as_ref().ok_or(CursorError::Unsupported)?;
+
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let barriers = xfixes
+                .create_barriers(self.display, root, position, size)
+                .ok_or(CursorError::Unsupported)?
+                .map_err(|_| CursorError::InvalidRegion(position, size))?;
+            self.confine_barriers.replace(barriers);
+
+            // Barriers only stop the pointer crossing them
+            let (x, y) = self.query_pointer().coordinates;
+            let inside = (
+                x.clamp(position.0, region_end(position.0, size.0)),
+                y.clamp(position.1, region_end(position.1, size.1)),
+            );
+            if inside != (x, y) {
+                xlib::XWarpPointer(self.display, 0, root, 0, 0, 0, 0, inside.0, inside.1);
+            }
+            xlib::XFlush(self.display);
+        }
+        Ok(())
+    }
+
+    /// Let the cursor leave the region set with `confine_cursor()`, returning whether
+    /// it was confined
+    pub fn release_cursor(&self) -> bool {
+        let barriers = self.confine_barriers.take();
+        match &self.xfixes {
+            Some(xfixes) if !barriers.is_empty() => unsafe {
+                xfixes.destroy_barriers(self.display, &barriers);
+                xlib::XFlush(self.display);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Hide the cursor on every window until `show_cursor()` is called or the
+    /// DeviceState is dropped. Returns whether the cursor is hidden, which requires
+    /// the XFixes 4.0 extension.
+    pub fn hide_cursor(&self) -> bool {
+        let xfixes = match &self.xfixes {
+            Some(xfixes) => xfixes,
+            None => return false,
+        };
+
+        // The server counts the calls, so hiding twice would take showing twice
+        if !self.cursor_hidden.replace(true) {
+            unsafe {
+                xfixes.hide_cursor(self.display, xlib::XDefaultRootWindow(self.display));
+                xlib::XFlush(self.display);
+            }
+        }
+        true
+    }
+
+    /// Show the cursor hidden with `hide_cursor()` again, returning whether it was hidden
+    pub fn show_cursor(&self) -> bool {
+        let xfixes = match &self.xfixes {
+            Some(xfixes) if self.cursor_hidden.replace(false) => xfixes,
+            _ => return false,
+        };
 
         unsafe {
+            xfixes.show_cursor(self.display, xlib::XDefaultRootWindow(self.display));
+            xlib::XFlush(self.display);
+        }
+        true
+    }
+
+    /// Get the picture of the cursor, to draw it over a screen capture at the
+    /// `query_mouse()` coordinates minus the hotspot. The image is available even
+    /// while the cursor is hidden. Requires the XFixes 4.0 extension.
+    pub fn get_cursor_image(&self) -> Option<CursorImage> {
+        let xfixes = self.xfixes.as_ref()?;
+
+        unsafe { xfixes.cursor_image(self.display) }
+    }
+
+    /// Get the changes of the cursor picture since the previous query, oldest first,
+    /// to know when to call `get_cursor_image()` again. Requires the XFixes 4.0 extension.
+    pub fn query_cursor_events(&self) -> Vec<CursorEvent> {
+        self.process_events();
+
+        self.cursor_events.borrow_mut().drain(..).collect()
+    }
+
+    /// Read the click thresholds from the desktop settings, keeping the defaults for
+    /// the ones the desktop does not share
+    pub fn query_click_settings(&self) -> ClickSettings {
+        let mut settings = ClickSettings::default();
+
+        if let Some(data) = unsafe { xsettings::fetch(self.display) } {
+            if let Some(time) = xsettings::parse_int(&data, "Net/DoubleClickTime") {
+                settings.multi_click_time = Duration::from_millis(time.max(0) as u64);
+            }
+            if let Some(distance) = xsettings::parse_int(&data, "Net/DoubleClickDistance") {
+                settings.click_distance = distance.max(0);
+            }
+        }
+
+        settings
+    }
+
+    /// Grab a chord on the whole display so that it is consumed: the focused application
+    /// stops receiving it while queries still see the keys pressed. The grab lasts until
+    /// `ungrab_hotkey()` is called or the DeviceState is dropped.
+    ///
+    /// Fails with `HotkeyError::AlreadyGrabbed` if another application grabbed the chord,
+    /// and with `HotkeyError::Ungrabbable` unless the chord has exactly one key.
+    /// The grab is renewed when the keyboard mapping changes. If the new mapping does not
+    /// allow it, the chord stays registered without being grabbed, the failure is
+    /// reported by `query_grab_errors()` and the grab is retried on the next change.
+    pub fn grab_hotkey(&self, hotkey: &Hotkey) -> Result<(), HotkeyError> {
+        if self
+            .grabs
+            .borrow()
+            .iter()
+            .any(|(grabbed, _)| grabbed == hotkey)
+        {
+            return Err(HotkeyError::Conflict(hotkey.clone()));
+        }
+
+        let key_grabs = self
+            .with_keyboard_mapping(|mapping| mapping.key_grabs(hotkey))
+            .ok_or_else(|| HotkeyError::Ungrabbable(hotkey.clone()))?;
+
+        match self.grab_keys(&key_grabs) {
+            None => {
+                self.grabs.borrow_mut().push((hotkey.clone(), key_grabs));
+                Ok(())
+            }
+            Some(error_code) => Err(grab_error(hotkey, error_code)),
+        }
+    }
+
+    /// Get the chords that could not be grabbed again after a change of the keyboard
+    /// mapping since the previous query, oldest first. They stay registered and are
+    /// retried on the next change, until `ungrab_hotkey()` is called.
+    pub fn query_grab_errors(&self) -> Vec<HotkeyError> {
+        self.process_events();
+
+        self.grab_errors.borrow_mut().drain(..).collect()
+    }
+
+    /// Release a chord grabbed with `grab_hotkey()`, returning whether it was grabbed
+    pub fn ungrab_hotkey(&self, hotkey: &Hotkey) -> bool {
+        let mut grabs = self.grabs.borrow_mut();
+
+        match grabs.iter().position(|(grabbed, _)| grabbed == hotkey) {
+            Some(ix) => {
+                let (_, key_grabs) = grabs.remove(ix);
+                self.ungrab_keys(&key_grabs);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Grab keys on the root window, returning the error code of the first failure.
+    /// Nothing stays grabbed after a failure.
+    fn grab_keys(&self, key_grabs: &[KeyGrab]) -> Option<u8> {
+        let (_, error_code) = unsafe {
             let root = xlib::XDefaultRootWindow(self.display);
 
+            xerror::trap_errors(self.display, || {
+                for (keycode, mask) in key_grabs {
+                    xlib::XGrabKey(
+                        self.display,
+                        *keycode,
+                        *mask,
+                        root,
+                        xlib::False,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                    );
+                }
+            })
+        };
+
+        if error_code.is_some() {
+            // Release the combinations that were grabbed before the failure
+            self.ungrab_keys(key_grabs);
+        }
+        error_code
+    }
+
+    /// Grab the chords again after a change of the keyboard mapping, which may have
+    /// moved their keys to other keycodes. The chords that cannot be grabbed are kept
+    /// without key grabs and reported by `query_grab_errors()`.
+    fn renew_grabs(&self) {
+        let grabs = self.grabs.take();
+        if grabs.is_empty() {
+            return;
+        }
+
+        let mut renewed = Vec::new();
+        for (hotkey, key_grabs) in grabs {
+            self.ungrab_keys(&key_grabs);
+
+            let key_grabs = self
+                .keyboard_mapping
+                .borrow_mut()
+                .get_or_insert_with(|| unsafe { KeyboardMapping::fetch(self.display) })
+                .key_grabs(&hotkey);
+            let result = match key_grabs {
+                Some(key_grabs) => match self.grab_keys(&key_grabs) {
+                    None => Ok(key_grabs),
+                    Some(error_code) => Err(grab_error(&hotkey, error_code)),
+                },
+                None => Err(HotkeyError::Ungrabbable(hotkey.clone())),
+            };
+
+            match result {
+                Ok(key_grabs) => renewed.push((hotkey, key_grabs)),
+                Err(error) => {
+                    let mut grab_errors = self.grab_errors.borrow_mut();
+                    if grab_errors.len() == MAX_GRAB_ERRORS {
+                        grab_errors.pop_front();
+                    }
+                    grab_errors.push_back(error);
+                    renewed.push((hotkey, Vec::new()));
+                }
+            }
+        }
+        self.grabs.replace(renewed);
+    }
+
+    fn ungrab_keys(&self, key_grabs: &[KeyGrab]) {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+
+            for (keycode, mask) in key_grabs {
+                xlib::XUngrabKey(self.display, *keycode, *mask, root);
+            }
+            xlib::XFlush(self.display);
+        }
+    }
+
+    /// Get the position of the pointer and the state of the buttons and modifiers,
+    /// from XI2 when available as the core protocol only reports the buttons up to 5
+    fn query_pointer(&self) -> Pointer {
+        let root = unsafe { xlib::XDefaultRootWindow(self.display) };
+        if let Some(xinput) = &self.xinput {
+            if let Some(pointer) = unsafe { xinput.query_pointer(self.display, root) } {
+                return pointer;
+            }
+        }
+
+        let (mut root_return, mut child_return) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y, mut mask_return) = (0, 0, 0, 0, 0);
+        let same_screen = unsafe {
             xlib::XQueryPointer(
                 self.display,
                 root,
-                ptr::null_mut(),
-                ptr::null_mut(),
-                ptr::null_mut(),
-                ptr::null_mut(),
+                &mut root_return,
+                &mut child_return,
+                &mut root_x,
+                &mut root_y,
                 &mut win_x,
                 &mut win_y,
                 &mut mask_return,
-            );
+            ) != 0
+        };
+
+        Pointer {
+            coordinates: (root_x, root_y),
+            buttons: core_buttons(mask_return),
+            modifiers: mask_return,
+            same_screen,
+            child: child_return,
         }
+    }
 
-        // Create the mouse button array
-        let buttons = {
-            use xlib::{Button1Mask, Button2Mask, Button3Mask, Button4Mask, Button5Mask};
+    /// Query the keymap and the pointer, sending both requests at once. `None` without
+    /// libxcb, or without libxcb-xinput when the pointer has to be queried with XI2.
+    fn query_pipelined_state(&self) -> Option<([c_char; 32], Pointer)> {
+        let xcb = self.xcb.as_ref()?;
+        unsafe {
+            let device = match &self.xinput {
+                Some(xinput) => Some(xinput.client_pointer(self.display)?),
+                None => None,
+            };
+            xcb.query_state(xlib::XDefaultRootWindow(self.display), device)
+        }
+    }
 
-            let mut buttons = Vec::with_capacity(5); // There will be 5 buttons, so
-                                                     // make a vector with that capacity
+    /// Complete a pointer query into a MouseState
+    fn mouse_state(&self, pointer: &Pointer, timestamp: Timestamp) -> MouseState {
+        let coordinates = pointer.coordinates;
+        let mouse = MouseState::from(coordinates, &self.mouse_buttons(&pointer.buttons))
+            .with_timestamp(timestamp);
 
-            // For each of the five mouse buttons, check whether it's pressed and
-            // push the status to the button vector
-            for button in &[
-                Button1Mask,
-                Button2Mask,
-                Button3Mask,
-                Button4Mask,
-                Button5Mask,
-            ] {
-                buttons.push(mask_return & button > 0);
-            }
+        // The monitors of other screens are not tracked
+        match self.monitor_at(coordinates.0, coordinates.1) {
+            Some(monitor) if pointer.same_screen => mouse.with_monitor(monitor),
+            _ => mouse,
+        }
+    }
 
-            // Convert a vector of booleans into an array with a length of 5
-            let create_button_arr = |arr: &[bool]| -> [bool; 5] {
-                let mut array = [false; 5];
-                let buttons = &arr[..array.len()]; // Will panic if the vector is shorter than 5 elements
+    /// Fetch the monitors of the default screen
+    unsafe fn fetch_monitors(&self) -> Vec<Monitor> {
+        let scale = self.query_scale();
+        let root = xlib::XDefaultRootWindow(self.display);
 
-                array.copy_from_slice(buttons);
-                array
-            };
+        let monitors = match &self.xrandr {
+            Some(xrandr) => xrandr.monitors(self.display, root, scale),
+            None => Vec::new(),
+        };
+        if !monitors.is_empty() {
+            return monitors;
+        }
 
-            create_button_arr(&buttons)
+        let screen = xlib::XDefaultScreen(self.display);
+        vec![Monitor::from(
+            &format!("Screen {}", screen),
+            (0, 0),
+            (
+                xlib::XDisplayWidth(self.display, screen).max(0) as u32,
+                xlib::XDisplayHeight(self.display, screen).max(0) as u32,
+            ),
+            true,
+            scale,
+        )
+        .with_physical_size((
+            xlib::XDisplayWidthMM(self.display, screen).max(0) as u32,
+            xlib::XDisplayHeightMM(self.display, screen).max(0) as u32,
+        ))]
+    }
+
+    /// Read the interface scale from the desktop settings, either the integer scale
+    /// of GTK or the font DPI, 1.0 if the desktop shares neither
+    fn query_scale(&self) -> f64 {
+        let data = match unsafe { xsettings::fetch(self.display) } {
+            Some(data) => data,
+            None => return 1.0,
         };
 
-        MouseState::from((win_x, win_y), buttons)
+        match xsettings::parse_int(&data, "Gdk/WindowScalingFactor") {
+            Some(factor) if factor > 0 => f64::from(factor),
+            _ => match xsettings::parse_int(&data, "Xft/DPI") {
+                // In 1024ths of a dot per inch
+                Some(dpi) if dpi > 0 => f64::from(dpi) / 1024.0 / 96.0,
+                _ => 1.0,
+            },
+        }
     }
 
-    /// Query the keyboard for all pressed keys, returned as a vector of KeyCodes
-    pub fn query_keymap(&self) -> Vec<KeyCode> {
-        let mut key_codes = Vec::new(); // Create vector to hold all key codes
-        let mut key_map: [std::os::raw::c_char; 32] = [0; 32]; // Create an empty key map array
+    /// Name the buttons held on the pointer, whose numbers come after the pointer mapping
+    fn mouse_buttons(&self, logical_numbers: &[u8]) -> Vec<MouseButton> {
+        let numbers = match self.button_mapping {
+            ButtonMapping::Logical => logical_numbers.to_vec(),
+            ButtonMapping::Physical => self.with_pointer_mapping(|pointer_mapping| {
+                logical_numbers
+                    .iter()
+                    .map(|logical| physical_button(pointer_mapping, *logical))
+                    .collect()
+            }),
+        };
+
+        numbers.into_iter().filter_map(mouse_button).collect()
+    }
+
+    /// Name the buttons held on a physical device, whose numbers come before the
+    /// pointer mapping
+    fn device_buttons(&self, physical_numbers: Vec<u8>) -> Vec<MouseButton> {
+        let numbers = match self.button_mapping {
+            ButtonMapping::Physical => physical_numbers,
+            ButtonMapping::Logical => self.with_pointer_mapping(|pointer_mapping| {
+                physical_numbers
+                    .into_iter()
+                    .map(|physical| logical_button(pointer_mapping, physical))
+                    .collect()
+            }),
+        };
+
+        numbers.into_iter().filter_map(mouse_button).collect()
+    }
+
+    /// Run a function with the pointer mapping, fetching it if it is not cached yet
+    fn with_pointer_mapping<T>(&self, f: impl FnOnce(&[u8]) -> T) -> T {
+        self.process_events();
+
+        let mut pointer_mapping = self.pointer_mapping.borrow_mut();
+        f(pointer_mapping
+            .get_or_insert_with(|| unsafe { Self::fetch_pointer_mapping(self.display) }))
+    }
+
+    /// Fetch the logical button of each physical button, the first one being button 1
+    unsafe fn fetch_pointer_mapping(display: *mut xlib::Display) -> Vec<u8> {
+        let mut map = [0; 256];
+        let len = xlib::XGetPointerMapping(display, map.as_mut_ptr(), map.len() as c_int);
+
+        map[..len.clamp(0, map.len() as c_int) as usize].to_vec()
+    }
+
+    /// Get the bit vector of pressed keycodes
+    fn query_raw_keymap(&self) -> [c_char; 32] {
+        let mut key_map: [c_char; 32] = [0; 32]; // Create an empty key map array
 
         unsafe {
             xlib::XQueryKeymap(self.display, key_map.as_mut_ptr()); // Query the OS for the key map and fill `key_map` with results
         }
 
-        for (ix, byte) in unsafe {
-            slice::from_raw_parts(key_map.as_mut_ptr(), 32)
-                .iter()
-                .enumerate()
-        } {
-            for bit in 0_u8..8_u8 {
-                let bitmask = 1 << bit;
+        key_map
+    }
 
-                if byte & bitmask != 0 {
-                    let keycode = ix as u8 * 8 + bit;
-                    let mut key_syms: i32 = 0;
-
-                    unsafe {
-                        let key_sym =
-                            xlib::XGetKeyboardMapping(self.display, keycode, 1, &mut key_syms);
-
-                        for ks in slice::from_raw_parts(key_sym, key_syms as usize).iter() {
-                            // Attempt to match KeyCode against keys and if
-                            // the key is matched push to the key_codes vector
-                            if let Some(k) = KeyCode::keysym_to_key(*ks as u32) {
-                                key_codes.push(k)
-                            };
-                        }
+    /// Run `f` with the cached keyboard mapping, fetching it first if the
+    /// server reported a change since the last query
+    fn with_keyboard_mapping<T>(&self, f: impl FnOnce(&KeyboardMapping) -> T) -> T {
+        self.process_events();
+
+        let mut keyboard_mapping = self.keyboard_mapping.borrow_mut();
+        f(keyboard_mapping.get_or_insert_with(|| unsafe { KeyboardMapping::fetch(self.display) }))
+    }
+
+    /// Get the size of the default screen, which spans every monitor
+    fn screen_size(&self) -> (u32, u32) {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            (
+                xlib::XDisplayWidth(self.display, screen).max(0) as u32,
+                xlib::XDisplayHeight(self.display, screen).max(0) as u32,
+            )
+        }
+    }
 
-                        // Free the memory allocated by XGetKeyboardMapping.
-                        xlib::XFree(key_sym as *mut std::ffi::c_void);
+    /// Drain the events queued on the display without blocking, reacting
+    /// to the ones that invalidate cached state
+    fn process_events(&self) {
+        unsafe {
+            while xlib::XPending(self.display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                xlib::XNextEvent(self.display, &mut event);
+
+                match event.get_type() {
+                    xlib::MappingNotify if event.mapping.request == xlib::MappingPointer => {
+                        self.pointer_mapping.borrow_mut().take();
+                    }
+                    xlib::MappingNotify => {
+                        xlib::XRefreshKeyboardMapping(&mut event.mapping);
+                        self.keyboard_mapping.borrow_mut().take();
+                        self.renew_grabs();
+                    }
+                    kind if Some(kind) == self.xkb_event_base => {
+                        let xkb_event =
+                            &*(&event as *const xlib::XEvent as *const xlib::XkbAnyEvent);
+
+                        if xkb_event.xkb_type == xlib::XkbNewKeyboardNotify
+                            || xkb_event.xkb_type == xlib::XkbMapNotify
+                        {
+                            self.keyboard_mapping.borrow_mut().take();
+                            self.renew_grabs();
+                        }
+                    }
+                    xlib::GenericEvent => {
+                        self.process_generic_event(&mut event.generic_event_cookie)
+                    }
+                    xlib::PropertyNotify if event.property.atom == self.net_active_window => {
+                        self.process_focus_change(&event.property)
+                    }
+                    _ => {
+                        if let Some(change) = self
+                            .xfixes
+                            .as_ref()
+                            .and_then(|xfixes| xfixes.process_event(&event))
+                        {
+                            self.process_cursor_change(change);
+                        }
+                        if let Some(xrandr) = &self.xrandr {
+                            if xrandr.process_event(&mut event) {
+                                self.monitors.borrow_mut().take();
+                            }
+                        }
                     }
                 }
             }
         }
+    }
+}
 
-        // Remove consecutive duplicates from the key code vector before returning it
-        key_codes.dedup();
-        key_codes
+impl DeviceState {
+    /// Record the devices a hierarchy event reports as added or removed
+    unsafe fn process_hierarchy_change(&self, xinput: &XInput, event: &xinput2::XIHierarchyEvent) {
+        let (added, removed) = xinput::hierarchy_changes(event);
+        xinput.forget_client_pointer();
+        for id in &removed {
+            xinput.forget_device(self.display, *id as xlib::XID);
+        }
+        // The touches of an unplugged device never end
+        self.touches.borrow_mut().retain(|touch| {
+            !removed
+                .iter()
+                .any(|id| Some(*id as u64) == touch.point.device())
+        });
+        let timestamp = Timestamp::from(Instant::now(), Some(event.time as u32));
+        let devices = if added.is_empty() {
+            Vec::new()
+        } else {
+            xinput.devices(self.display)
+        };
+
+        let added = devices
+            .into_iter()
+            .filter(|device| added.iter().any(|id| *id as u64 == device.id()))
+            .map(|device| DeviceEvent::DeviceAdded { device, timestamp });
+        let removed = removed.into_iter().map(|id| DeviceEvent::DeviceRemoved {
+            id: id as u64,
+            timestamp,
+        });
+
+        self.record_device_events(removed.chain(added));
+    }
+
+    /// Ask for more kinds of raw events, unless they are already followed
+    fn follow_raw_events(&self, update: impl FnOnce(&mut RawEvents)) {
+        let mut raw_events = self.raw_events.get();
+        update(&mut raw_events);
+        if raw_events == self.raw_events.get() {
+            return;
+        }
+
+        self.raw_events.set(raw_events);
+        if let Some(xinput) = &self.xinput {
+            unsafe {
+                let root = xlib::XDefaultRootWindow(self.display);
+                xinput.select_raw_events(self.display, root, raw_events);
+                xlib::XFlush(self.display);
+            }
+        }
+    }
+
+    /// Open the gamepads plugged in and record them as well as the unplugged ones.
+    /// The first call opens the gamepads already there without recording them.
+    fn update_gamepads(&self) {
+        let (added, removed) = match &mut *self.gamepads.borrow_mut() {
+            Some(gamepads) => gamepads.update(),
+            gamepads => {
+                *gamepads = Some(Gamepads::open());
+                return;
+            }
+        };
+        let timestamp = Timestamp::now();
+
+        let removed = removed
+            .into_iter()
+            .map(|id| DeviceEvent::GamepadDisconnected { id, timestamp });
+        let added = added
+            .into_iter()
+            .map(|(id, name)| DeviceEvent::GamepadConnected {
+                id,
+                name,
+                timestamp,
+            });
+        self.record_device_events(removed.chain(added));
+    }
+
+    /// Queue device events for `query_device_events()`, dropping the oldest ones
+    fn record_device_events(&self, events: impl Iterator<Item = DeviceEvent>) {
+        let mut device_events = self.device_events.borrow_mut();
+        for event in events {
+            if device_events.len() == MAX_DEVICE_EVENTS {
+                device_events.pop_front();
+            }
+            device_events.push_back(event);
+        }
+    }
+
+    /// Record the scrolling of a raw button press or motion event
+    unsafe fn process_scroll(&self, xinput: &XInput, event: &xinput2::XIRawEvent) {
+        let mut scroll_valuators = self.scroll_valuators.borrow_mut();
+        let scroll_valuators =
+            scroll_valuators.get_or_insert_with(|| xinput.scroll_valuators(self.display));
+
+        if let Some(delta) = xinput::raw_scroll(event, scroll_valuators) {
+            let mut scroll_events = self.scroll_events.borrow_mut();
+            if scroll_events.len() == MAX_SCROLL_EVENTS {
+                scroll_events.pop_front();
+            }
+
+            let timestamp = Timestamp::from(Instant::now(), Some(event.time as u32));
+            scroll_events
+                .push_back(ScrollEvent::from(delta, timestamp).with_device(event.sourceid as u64));
+        }
+    }
+
+    /// Record the movement of a raw motion event
+    unsafe fn process_motion(&self, xinput: &XInput, event: &xinput2::XIRawEvent) {
+        let mut relative_devices = self.relative_devices.borrow_mut();
+        let relative_devices =
+            relative_devices.get_or_insert_with(|| xinput.relative_devices(self.display));
+
+        if let Some(delta) = xinput::raw_motion(event, relative_devices) {
+            let mut motion_events = self.motion_events.borrow_mut();
+            if motion_events.len() == MAX_MOTION_EVENTS {
+                motion_events.pop_front();
+            }
+
+            let timestamp = Timestamp::from(Instant::now(), Some(event.time as u32));
+            motion_events
+                .push_back(MotionEvent::from(delta, timestamp).with_device(event.sourceid as u64));
+        }
+    }
+
+    /// Follow a touch through a raw touch event
+    unsafe fn process_touch(&self, xinput: &XInput, event: &xinput2::XIRawEvent) {
+        let mut touches = self.touches.borrow_mut();
+        let (id, device) = (event.detail as u32 as u64, event.sourceid as u64);
+        let index = touches
+            .iter()
+            .position(|touch| touch.point.id() == id && touch.point.device() == Some(device));
+
+        if event.evtype == xinput2::XI_RawTouchEnd {
+            if let Some(index) = index {
+                touches.remove(index);
+            }
+            return;
+        }
+
+        let mut touch_valuators = self.touch_valuators.borrow_mut();
+        let touch_valuators =
+            touch_valuators.get_or_insert_with(|| xinput.touch_valuators(self.display));
+        let valuators = match touch_valuators.get(&event.sourceid) {
+            Some(valuators) => valuators,
+            None => return,
+        };
+
+        // The coordinate transformation matrix maps touchscreens to fractions of
+        // the whole screen
+        let size = self.screen_size();
+
+        match index {
+            Some(index) => {
+                touches[index] = xinput::raw_touch(event, valuators, size, Some(&touches[index]))
+            }
+            None => touches.push(xinput::raw_touch(event, valuators, size, None)),
+        }
+    }
+
+    /// Record a change of the cursor
+    unsafe fn process_cursor_change(&self, change: CursorChange) {
+        let name = property::atom_name(self.display, change.name);
+        let timestamp = Timestamp::from(Instant::now(), Some(change.time as u32));
+
+        let mut cursor_events = self.cursor_events.borrow_mut();
+        if cursor_events.len() == MAX_CURSOR_EVENTS {
+            cursor_events.pop_front();
+        }
+        cursor_events.push_back(CursorEvent::from(change.serial, &name, timestamp));
+    }
+
+    /// Record a change of the active window, ignoring the window manager setting it
+    /// again to the same window
+    unsafe fn process_focus_change(&self, event: &xlib::XPropertyEvent) {
+        let focused = window::focused_window(self.display, event.window);
+        if focused == self.focused_window.replace(focused) {
+            return;
+        }
+
+        let window = focused.and_then(|focused| window::window_info(self.display, focused));
+        let timestamp = Timestamp::from(Instant::now(), Some(event.time as u32));
+
+        let mut focus_events = self.focus_events.borrow_mut();
+        if focus_events.len() == MAX_FOCUS_EVENTS {
+            focus_events.pop_front();
+        }
+        focus_events.push_back(FocusEvent::from(window, timestamp));
+    }
+
+    /// React to an XI2 event
+    unsafe fn process_generic_event(&self, cookie: &mut xlib::XGenericEventCookie) {
+        let xinput = match &self.xinput {
+            Some(xinput) if cookie.extension == xinput.opcode() => xinput,
+            _ => return,
+        };
+        if xlib::XGetEventData(self.display, cookie) == 0 {
+            return;
+        }
+
+        match cookie.evtype {
+            xinput2::XI_HierarchyChanged | xinput2::XI_DeviceChanged => {
+                self.scroll_valuators.borrow_mut().take();
+                self.relative_devices.borrow_mut().take();
+                self.touch_valuators.borrow_mut().take();
+
+                if cookie.evtype == xinput2::XI_HierarchyChanged {
+                    let event = &*(cookie.data as *const xinput2::XIHierarchyEvent);
+                    self.process_hierarchy_change(xinput, event);
+                }
+            }
+            xinput2::XI_PropertyEvent => {
+                self.touch_valuators.borrow_mut().take();
+            }
+            xinput2::XI_RawButtonPress | xinput2::XI_RawMotion => {
+                let raw_event = &*(cookie.data as *const xinput2::XIRawEvent);
+                let raw_events = self.raw_events.get();
+                if raw_events.scroll {
+                    self.process_scroll(xinput, raw_event);
+                }
+                if raw_events.motion {
+                    self.process_motion(xinput, raw_event);
+                }
+            }
+            xinput2::XI_RawTouchBegin | xinput2::XI_RawTouchUpdate | xinput2::XI_RawTouchEnd => {
+                let raw_event = &*(cookie.data as *const xinput2::XIRawEvent);
+                self.process_touch(xinput, raw_event);
+            }
+            _ => {}
+        }
+
+        xlib::XFreeEventData(self.display, cookie);
+    }
+}
+
+/// Get the held buttons from a core pointer state mask, which only holds the buttons
+/// up to 5, the wheel being 4 and 5
+pub(super) fn core_buttons(mask: c_uint) -> Vec<u8> {
+    [
+        (1, xlib::Button1Mask),
+        (2, xlib::Button2Mask),
+        (3, xlib::Button3Mask),
+    ]
+    .iter()
+    .filter(|(_, button_mask)| mask & button_mask != 0)
+    .map(|(number, _)| *number)
+    .collect()
+}
+
+/// Get the last pixel of a region along an axis
+fn region_end(start: i32, length: u32) -> i32 {
+    start.saturating_add(length.min(i32::MAX as u32) as i32 - 1)
+}
+
+/// Name the reason a chord could not be grabbed from the error code of the grab
+fn grab_error(hotkey: &Hotkey, error_code: u8) -> HotkeyError {
+    if error_code == xlib::BadAccess {
+        HotkeyError::AlreadyGrabbed(hotkey.clone())
+    } else {
+        HotkeyError::Ungrabbable(hotkey.clone())
+    }
+}
+
+/// Find the logical button a physical one produces in a pointer mapping, the
+/// physical button itself if the mapping does not cover it
+fn logical_button(pointer_mapping: &[u8], physical: u8) -> u8 {
+    match physical
+        .checked_sub(1)
+        .and_then(|i| pointer_mapping.get(i as usize))
+    {
+        Some(logical) => *logical,
+        None => physical,
     }
-}
\ No newline at end of file
+}
+
+/// Find the physical button producing a logical one in a pointer mapping,
+/// the logical button itself if none does
+fn physical_button(pointer_mapping: &[u8], logical: u8) -> u8 {
+    pointer_mapping
+        .iter()
+        .position(|button| *button == logical)
+        .map_or(logical, |ix| ix as u8 + 1)
+}
+
+/// Name an X button. Buttons 4 to 7 are wheel notches rather than buttons.
+fn mouse_button(number: u8) -> Option<MouseButton> {
+    match number {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        4..=7 => None,
+        8 => Some(MouseButton::Back),
+        9 => Some(MouseButton::Forward),
+        number => Some(MouseButton::Other(number)),
+    }
+}
+
+impl Default for DeviceState {
+    /// Create a new DeviceState, panicking if the X display cannot be opened
+    fn default() -> Self {
+        Self::new().expect("Failed to create DeviceState")
+    }
+}
+
+impl Drop for DeviceState {
+    /// Close the display, which also releases the grabs held by the DeviceState
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(xinput) = &self.xinput {
+                xinput.close_devices(self.display);
+            }
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11::keysym;
+
+    #[test]
+    fn key_grabs_test() {
+        // K on keycode 45, Num Lock bound to Mod2
+        let mut keysyms = vec![0; 2 * 50];
+        keysyms[2 * (45 - 8)] = keysym::XK_k as xlib::KeySym;
+        keysyms[2 * (45 - 8) + 1] = keysym::XK_K as xlib::KeySym;
+        let mut mapping = KeyboardMapping {
+            min_keycode: 8,
+            keysyms_per_keycode: 2,
+            keysyms,
+            alt_mask: xlib::Mod1Mask,
+            meta_mask: 0,
+            num_lock_mask: xlib::Mod2Mask,
+        };
+
+        let mask = xlib::ControlMask | xlib::Mod1Mask;
+        assert_eq!(
+            mapping.key_grabs(&"Ctrl+Alt+K".parse().unwrap()),
+            Some(vec![
+                (45, mask),
+                (45, mask | xlib::LockMask),
+                (45, mask | xlib::Mod2Mask),
+                (45, mask | xlib::Mod2Mask | xlib::LockMask),
+            ])
+        );
+        assert_eq!(mapping.key_grabs(&"Meta+K".parse().unwrap()), None);
+        assert_eq!(mapping.key_grabs(&"Ctrl+K+L".parse().unwrap()), None);
+        assert_eq!(mapping.key_grabs(&"Ctrl+L".parse().unwrap()), None);
+
+        // Without Num Lock, only Caps Lock is ignored
+        mapping.num_lock_mask = 0;
+        assert_eq!(
+            mapping.key_grabs(&"Shift+K".parse().unwrap()),
+            Some(vec![
+                (45, xlib::ShiftMask),
+                (45, xlib::ShiftMask | xlib::LockMask)
+            ])
+        );
+    }
+
+    #[test]
+    fn grab_error_test() {
+        let hotkey: Hotkey = "Ctrl+K".parse().unwrap();
+
+        assert_eq!(
+            grab_error(&hotkey, xlib::BadAccess),
+            HotkeyError::AlreadyGrabbed(hotkey.clone())
+        );
+        assert_eq!(
+            grab_error(&hotkey, xlib::BadValue),
+            HotkeyError::Ungrabbable(hotkey.clone())
+        );
+    }
+
+    #[test]
+    fn mouse_button_test() {
+        let buttons: Vec<Option<MouseButton>> = (1..=10).map(mouse_button).collect();
+
+        assert_eq!(
+            buttons,
+            vec![
+                Some(MouseButton::Left),
+                Some(MouseButton::Middle),
+                Some(MouseButton::Right),
+                None,
+                None,
+                None,
+                None,
+                Some(MouseButton::Back),
+                Some(MouseButton::Forward),
+                Some(MouseButton::Other(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn core_buttons_test() {
+        let mask = xlib::Button1Mask | xlib::Button3Mask | xlib::Button4Mask | xlib::ShiftMask;
+
+        assert_eq!(core_buttons(mask), vec![1, 3]);
+        assert_eq!(core_buttons(0), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn logical_button_test() {
+        // Left-handed mapping
+        let pointer_mapping = [3, 2, 1, 4, 5];
+
+        assert_eq!(logical_button(&pointer_mapping, 1), 3);
+        assert_eq!(logical_button(&pointer_mapping, 3), 1);
+        assert_eq!(logical_button(&pointer_mapping, 8), 8);
+        assert_eq!(logical_button(&pointer_mapping, 0), 0);
+    }
+
+    #[test]
+    fn physical_button_test() {
+        // `xmodmap -e "pointer = 3 2 1"` for left-handed use
+        let left_handed = [3, 2, 1, 4, 5, 6, 7, 8, 9];
+
+        assert_eq!(physical_button(&left_handed, 3), 1);
+        assert_eq!(physical_button(&left_handed, 1), 3);
+        assert_eq!(physical_button(&left_handed, 8), 8);
+        assert_eq!(physical_button(&left_handed, 12), 12);
+
+        // Back and forward swapped, button 9 disabled
+        let custom = [1, 2, 3, 4, 5, 6, 7, 9, 0];
+        assert_eq!(physical_button(&custom, 9), 8);
+    }
+}
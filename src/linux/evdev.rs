@@ -0,0 +1,433 @@
+//! Reading gamepads and joysticks from the Linux event devices in `/dev/input`,
+//! which X does not handle
+
+use crate::{GamepadButton, GamepadState, Timestamp};
+use libc::{IN_ATTRIB, IN_CLOEXEC, IN_CREATE, IN_DELETE, IN_NONBLOCK, IN_Q_OVERFLOW};
+use std::{
+    ffi::CString,
+    fs::{self, File},
+    io::Read,
+    mem,
+    os::unix::io::{AsRawFd, FromRawFd},
+    path::Path,
+};
+
+const INPUT_DIR: &str = "/dev/input";
+
+/// The size of `struct inotify_event` without the name following it
+const INOTIFY_EVENT_SIZE: usize = mem::size_of::<libc::inotify_event>();
+
+const EV_KEY: u8 = 0x01;
+const EV_ABS: u8 = 0x03;
+const KEY_BYTES: usize = 0x300 / 8;
+const ABS_BYTES: usize = 0x40 / 8;
+
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_TL2: u16 = 0x138;
+const BTN_TR2: u16 = 0x139;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_GAS: u16 = 0x09;
+const ABS_BRAKE: u16 = 0x0a;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+/// The range and value of an absolute axis, `struct input_absinfo`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct AbsInfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// The gamepads and joysticks this process may open, kept open between queries.
+///
+/// `/dev/input` is watched with inotify, so that only the event devices that appear
+/// there are opened to tell whether they are gamepads. Without inotify, the directory
+/// is listed again on every update, still only opening the new devices.
+pub(super) struct Gamepads {
+    inotify: Option<File>,
+    gamepads: Vec<Gamepad>,
+}
+
+impl Gamepads {
+    /// Open the gamepads and start watching for new ones
+    pub(super) fn open() -> Gamepads {
+        let mut gamepads = Gamepads {
+            inotify: watch(INPUT_DIR),
+            gamepads: Vec::new(),
+        };
+        gamepads.rescan();
+        gamepads
+    }
+
+    /// Open the gamepads that appeared and forget those that disappeared since the
+    /// previous update, returning the ids and names of the former and the ids of the latter
+    pub(super) fn update(&mut self) -> (Vec<(u64, String)>, Vec<u64>) {
+        let changes = match self.inotify.as_mut().map(read_changes) {
+            Some(Some(changes)) => changes,
+            // Without inotify or after missing changes, compare with the directory
+            _ => return self.rescan(),
+        };
+
+        let (mut added, mut removed) = (Vec::new(), Vec::new());
+        for (id, exists) in changes {
+            let index = self.gamepads.iter().position(|gamepad| gamepad.id == id);
+            match (index, exists) {
+                (Some(index), false) => removed.push(self.gamepads.remove(index).id),
+                (None, true) => added.extend(self.add(id)),
+                _ => {}
+            }
+        }
+        (added, removed)
+    }
+
+    /// Read the state of the gamepads, ordered by id. Gamepads that cannot be read
+    /// any more are left out until the next update forgets them.
+    pub(super) fn states(&self) -> Vec<GamepadState> {
+        let mut states: Vec<GamepadState> =
+            self.gamepads.iter().filter_map(Gamepad::state).collect();
+        states.sort_by_key(|state| state.id());
+        states
+    }
+
+    /// Open the gamepads of the directory that are not open yet and forget those
+    /// that are gone, returning them as `update()` does
+    fn rescan(&mut self) -> (Vec<(u64, String)>, Vec<u64>) {
+        let ids: Vec<u64> = match fs::read_dir(INPUT_DIR) {
+            Ok(entries) => entries
+                .filter_map(|entry| event_device_id(entry.ok()?.file_name().to_str()?))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let mut removed = Vec::new();
+        self.gamepads.retain(|gamepad| {
+            let exists = ids.contains(&gamepad.id);
+            if !exists {
+                removed.push(gamepad.id);
+            }
+            exists
+        });
+
+        let mut added = Vec::new();
+        for id in ids {
+            if !self.gamepads.iter().any(|gamepad| gamepad.id == id) {
+                added.extend(self.add(id));
+            }
+        }
+        (added, removed)
+    }
+
+    /// Open an event device and keep it if it is a gamepad, returning its id and name then
+    fn add(&mut self, id: u64) -> Option<(u64, String)> {
+        let gamepad = Gamepad::open(id)?;
+        let name = gamepad.name.clone();
+        self.gamepads.push(gamepad);
+        Some((id, name))
+    }
+}
+
+/// An event device found to be a gamepad
+struct Gamepad {
+    id: u64,
+    name: String,
+    file: File,
+    abs_bits: [u8; ABS_BYTES],
+}
+
+impl Gamepad {
+    /// Open `/dev/input/event<id>`, `None` if it cannot be opened or is not a gamepad
+    fn open(id: u64) -> Option<Gamepad> {
+        let file = File::open(Path::new(INPUT_DIR).join(format!("event{}", id))).ok()?;
+
+        let key_bits: [u8; KEY_BYTES] = read(&file, 0x20 + u32::from(EV_KEY))?;
+        let abs_bits: [u8; ABS_BYTES] = read(&file, 0x20 + u32::from(EV_ABS))?;
+        if !is_gamepad(&key_bits, &abs_bits) {
+            return None;
+        }
+
+        let name: [u8; 256] = read(&file, 0x06)?;
+        let name_len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+
+        Some(Gamepad {
+            id,
+            name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+            file,
+            abs_bits,
+        })
+    }
+
+    /// Read the state of the gamepad, `None` if it was unplugged
+    fn state(&self) -> Option<GamepadState> {
+        let (id, file, abs_bits) = (self.id, &self.file, &self.abs_bits);
+        let timestamp = Timestamp::now();
+        let held: [u8; KEY_BYTES] = read(file, 0x18)?;
+
+        let axis = |code: u16| -> Option<AbsInfo> {
+            if !bit_set(abs_bits, code) {
+                return None;
+            }
+            read(file, 0x40 + u32::from(code))
+        };
+        let stick = |x, y| {
+            (
+                axis(x).map_or(0.0, |info| stick_value(&info)),
+                axis(y).map_or(0.0, |info| stick_value(&info)),
+            )
+        };
+        let trigger = |codes: [u16; 2], button| match codes.iter().find_map(|code| axis(*code)) {
+            Some(info) => trigger_value(&info),
+            None if bit_set(&held, button) => 1.0,
+            None => 0.0,
+        };
+
+        let buttons: Vec<GamepadButton> = (BTN_JOYSTICK..=BTN_THUMBR)
+            .filter(|code| bit_set(&held, *code))
+            .map(gamepad_button)
+            .collect();
+        let hat = |code| axis(code).map_or(0, |info| info.value.signum() as i8);
+        let pressed = |code| i8::from(bit_set(&held, code));
+        let dpad = (
+            (hat(ABS_HAT0X) + pressed(BTN_DPAD_RIGHT) - pressed(BTN_DPAD_LEFT)).signum(),
+            (hat(ABS_HAT0Y) + pressed(BTN_DPAD_DOWN) - pressed(BTN_DPAD_UP)).signum(),
+        );
+
+        Some(
+            GamepadState::from(id, &self.name, &buttons)
+                .with_sticks(stick(ABS_X, ABS_Y), stick(ABS_RX, ABS_RY))
+                .with_triggers(
+                    trigger([ABS_Z, ABS_BRAKE], BTN_TL2),
+                    trigger([ABS_RZ, ABS_GAS], BTN_TR2),
+                )
+                .with_dpad(dpad)
+                .with_timestamp(timestamp),
+        )
+    }
+}
+
+/// Watch a directory for files being created, deleted or given other permissions,
+/// `None` if inotify is unavailable
+fn watch(dir: &str) -> Option<File> {
+    let dir = CString::new(dir).ok()?;
+    unsafe {
+        let fd = libc::inotify_init1(IN_NONBLOCK | IN_CLOEXEC);
+        if fd < 0 {
+            return None;
+        }
+        // Owning the descriptor closes it if the watch fails
+        let inotify = File::from_raw_fd(fd);
+
+        if libc::inotify_add_watch(fd, dir.as_ptr(), IN_ATTRIB | IN_CREATE | IN_DELETE) < 0 {
+            return None;
+        }
+        Some(inotify)
+    }
+}
+
+/// Read the pending inotify events, `None` if some were lost and the directory has to
+/// be listed again
+fn read_changes(inotify: &mut File) -> Option<Vec<(u64, bool)>> {
+    let mut changes = Vec::new();
+    let mut buffer = [0; 4096];
+
+    // Reading fails with `WouldBlock` once no event is left
+    while let Ok(length) = inotify.read(&mut buffer) {
+        if length == 0 {
+            break;
+        }
+        changes.extend(inotify_changes(&buffer[..length])?);
+    }
+    Some(changes)
+}
+
+/// Get the event devices inotify events report, each with whether it exists after
+/// the event, `None` if the event queue overflowed
+fn inotify_changes(mut buffer: &[u8]) -> Option<Vec<(u64, bool)>> {
+    let mut changes = Vec::new();
+    let field = |bytes: &[u8], offset: usize| {
+        let mut field = [0; 4];
+        field.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_ne_bytes(field)
+    };
+
+    while buffer.len() >= INOTIFY_EVENT_SIZE {
+        let mask = field(buffer, 4);
+        let end = (INOTIFY_EVENT_SIZE + field(buffer, 12) as usize).min(buffer.len());
+        if mask & IN_Q_OVERFLOW != 0 {
+            return None;
+        }
+
+        // The name is padded with zeros
+        let name = &buffer[INOTIFY_EVENT_SIZE..end];
+        let name_len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+        let id = std::str::from_utf8(&name[..name_len])
+            .ok()
+            .and_then(event_device_id);
+        if let Some(id) = id {
+            changes.push((id, mask & IN_DELETE == 0));
+        }
+
+        buffer = &buffer[end..];
+    }
+    Some(changes)
+}
+
+/// Get the number of an event device from its file name, such as 3 for `event3`
+fn event_device_id(name: &str) -> Option<u64> {
+    name.strip_prefix("event")?.parse().ok()
+}
+
+/// Read a value from an event device with the ioctl request of this number, the
+/// `_IOR('E', nr, T)` of the kernel headers, whose encoding depends on the architecture
+fn read<T: Copy>(file: &File, nr: u32) -> Option<T> {
+    let mut value = unsafe { mem::zeroed::<T>() };
+    let request = libc::_IOR::<T>(u32::from(b'E'), nr);
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request, &mut value as *mut T) };
+
+    Some(value).filter(|_| result >= 0)
+}
+
+fn bit_set(bits: &[u8], bit: u16) -> bool {
+    matches!(bits.get(bit as usize / 8), Some(byte) if byte & (1 << (bit % 8)) != 0)
+}
+
+/// Check whether a device is a gamepad or a joystick: it has the X and Y axes and
+/// buttons from the joystick and gamepad ranges, unlike the motion sensors and
+/// touchpads some gamepads also expose
+fn is_gamepad(key_bits: &[u8], abs_bits: &[u8]) -> bool {
+    bit_set(abs_bits, ABS_X)
+        && bit_set(abs_bits, ABS_Y)
+        && (BTN_JOYSTICK..=BTN_THUMBR).any(|code| bit_set(key_bits, code))
+}
+
+/// Name a button from the joystick and gamepad ranges
+fn gamepad_button(code: u16) -> GamepadButton {
+    match code {
+        0x130 => GamepadButton::South,
+        0x131 => GamepadButton::East,
+        0x133 => GamepadButton::North,
+        0x134 => GamepadButton::West,
+        0x136 => GamepadButton::LeftBumper,
+        0x137 => GamepadButton::RightBumper,
+        BTN_TL2 => GamepadButton::LeftTrigger,
+        BTN_TR2 => GamepadButton::RightTrigger,
+        0x13a => GamepadButton::Select,
+        0x13b => GamepadButton::Start,
+        0x13c => GamepadButton::Mode,
+        0x13d => GamepadButton::LeftStick,
+        0x13e => GamepadButton::RightStick,
+        code => GamepadButton::Other(code),
+    }
+}
+
+/// Scale a stick axis to -1.0..=1.0, 0.0 within the flat zone around its center
+fn stick_value(info: &AbsInfo) -> f64 {
+    let (minimum, maximum) = (f64::from(info.minimum), f64::from(info.maximum));
+    if maximum <= minimum {
+        return 0.0;
+    }
+
+    let offset = f64::from(info.value) - (minimum + maximum) / 2.0;
+    if offset.abs() <= f64::from(info.flat) {
+        return 0.0;
+    }
+    (offset / ((maximum - minimum) / 2.0)).clamp(-1.0, 1.0)
+}
+
+/// Scale a trigger axis to 0.0..=1.0
+fn trigger_value(info: &AbsInfo) -> f64 {
+    let (minimum, maximum) = (f64::from(info.minimum), f64::from(info.maximum));
+    if maximum <= minimum {
+        return 0.0;
+    }
+
+    ((f64::from(info.value) - minimum) / (maximum - minimum)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis(value: i32, minimum: i32, maximum: i32, flat: i32) -> AbsInfo {
+        AbsInfo {
+            value,
+            minimum,
+            maximum,
+            flat,
+            ..AbsInfo::default()
+        }
+    }
+
+    #[test]
+    fn axis_value_test() {
+        assert_eq!(stick_value(&axis(-32768, -32768, 32767, 128)), -1.0);
+        assert_eq!(stick_value(&axis(100, -32768, 32767, 128)), 0.0);
+        assert_eq!(stick_value(&axis(255, 0, 255, 15)), 1.0);
+        assert_eq!(stick_value(&axis(64, 0, 256, 0)), -0.5);
+
+        assert_eq!(trigger_value(&axis(0, 0, 1023, 0)), 0.0);
+        assert_eq!(trigger_value(&axis(510, 0, 1020, 0)), 0.5);
+        assert_eq!(trigger_value(&axis(5, 5, 5, 0)), 0.0);
+    }
+
+    #[test]
+    fn inotify_changes_test() {
+        // Names padded with zeros to 16 bytes
+        let event = |mask: u32, name: &str| {
+            let mut event = Vec::new();
+            for field in &[1, mask, 0, 16] {
+                event.extend_from_slice(&field.to_ne_bytes());
+            }
+            event.extend_from_slice(name.as_bytes());
+            event.resize(INOTIFY_EVENT_SIZE + 16, 0);
+            event
+        };
+
+        let buffer = [
+            event(IN_CREATE, "event12"),
+            event(IN_CREATE, "js0"),
+            event(IN_ATTRIB, "event12"),
+            event(IN_DELETE, "event3"),
+        ]
+        .concat();
+        assert_eq!(
+            inotify_changes(&buffer),
+            Some(vec![(12, true), (12, true), (3, false)])
+        );
+        assert_eq!(inotify_changes(&event(IN_Q_OVERFLOW, "")), None);
+        assert_eq!(event_device_id("event7"), Some(7));
+        assert_eq!(event_device_id("mouse0"), None);
+    }
+
+    #[test]
+    fn is_gamepad_test() {
+        let mut key_bits = [0; KEY_BYTES];
+        let mut abs_bits = [0; ABS_BYTES];
+        abs_bits[0] = 0b11;
+        assert!(!is_gamepad(&key_bits, &abs_bits));
+
+        // BTN_SOUTH
+        key_bits[0x130 / 8] = 1;
+        assert!(is_gamepad(&key_bits, &abs_bits));
+        assert_eq!(gamepad_button(0x130), GamepadButton::South);
+        assert_eq!(gamepad_button(0x120), GamepadButton::Other(0x120));
+
+        abs_bits[0] = 0b01;
+        assert!(!is_gamepad(&key_bits, &abs_bits));
+    }
+}
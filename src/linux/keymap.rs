@@ -65,6 +65,12 @@ pub enum KeyCode {
     LAlt,
     RAlt,
     Enter,
+
+    // Arrow keys
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
 impl KeyCode {
@@ -139,6 +145,12 @@ impl KeyCode {
             keysym::XK_Alt_R => Some(KeyCode::RAlt),
             keysym::XK_Return => Some(KeyCode::Enter),
 
+            // Arrow keys
+            keysym::XK_Up => Some(KeyCode::Up),
+            keysym::XK_Down => Some(KeyCode::Down),
+            keysym::XK_Left => Some(KeyCode::Left),
+            keysym::XK_Right => Some(KeyCode::Right),
+
             // Return `None` if no match is found
             _ => None,
         }
@@ -0,0 +1,55 @@
+//! Loading the optional X extension libraries at runtime, so that a missing
+//! library disables a feature instead of preventing the program from starting
+
+use std::{
+    ffi::{c_void, CString},
+    mem,
+    os::raw::{c_char, c_int},
+};
+
+const RTLD_NOW: c_int = 2;
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlclose(handle: *mut c_void) -> c_int;
+}
+
+/// A shared library, closed when dropped
+pub(super) struct Library {
+    handle: *mut c_void,
+}
+
+impl Library {
+    /// Open the first library of `names` that can be found, such as `["libXi.so.6", "libXi.so"]`
+    pub(super) fn open(names: &[&str]) -> Option<Library> {
+        names.iter().find_map(|name| {
+            let name = CString::new(*name).ok()?;
+            let handle = unsafe { dlopen(name.as_ptr(), RTLD_NOW) };
+
+            Some(Library { handle }).filter(|library| !library.handle.is_null())
+        })
+    }
+
+    /// Look a function up. `T` must be the `unsafe extern "C" fn` type of the symbol.
+    pub(super) unsafe fn function<T: Copy>(&self, name: &str) -> Option<T> {
+        assert_eq!(mem::size_of::<T>(), mem::size_of::<*mut c_void>());
+
+        let name = CString::new(name).ok()?;
+        let symbol = dlsym(self.handle, name.as_ptr());
+
+        if symbol.is_null() {
+            None
+        } else {
+            Some(mem::transmute_copy(&symbol))
+        }
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            dlclose(self.handle);
+        }
+    }
+}
@@ -1,7 +1,17 @@
 //! The Linux version of the internals
 
 mod devicestate;
+mod evdev;
 mod keymap;
+mod library;
+mod property;
+mod window;
+mod xcb;
+mod xerror;
+mod xfixes;
+mod xinput;
+mod xrandr;
+mod xsettings;
 
 pub use devicestate::DeviceState;
 pub use keymap::KeyCode;
@@ -0,0 +1,113 @@
+//! Reading window properties
+
+use super::xerror;
+use std::{
+    convert::TryInto,
+    ffi::{CStr, CString},
+    mem,
+    os::raw::{c_int, c_long, c_uchar},
+    ptr, slice,
+};
+use x11::xlib;
+
+/// The raw value of a window property
+pub(super) struct Property {
+    format: c_int,
+    data: Vec<u8>,
+}
+
+impl Property {
+    /// Get the value of a property holding 8-bit items, such as strings
+    pub(super) fn bytes(&self) -> Option<&[u8]> {
+        Some(&self.data[..]).filter(|_| self.format == 8)
+    }
+
+    /// Get the value of a property holding 32-bit items, such as windows or cardinals,
+    /// which Xlib stores as longs
+    pub(super) fn longs(&self) -> Option<Vec<c_long>> {
+        if self.format != 32 {
+            return None;
+        }
+
+        Some(
+            self.data
+                .chunks_exact(mem::size_of::<c_long>())
+                .map(|bytes| c_long::from_ne_bytes(bytes.try_into().expect("Chunks are long")))
+                .collect(),
+        )
+    }
+}
+
+/// Get the atom of a name, creating it if it does not exist yet
+pub(super) unsafe fn intern_atom(display: *mut xlib::Display, name: &str) -> xlib::Atom {
+    let name = CString::new(name).expect("Atom names do not contain nul bytes");
+
+    xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+}
+
+/// Get the name of an atom, empty if it has none
+pub(super) unsafe fn atom_name(display: *mut xlib::Display, atom: xlib::Atom) -> String {
+    // Asking for the name of `None` is an error
+    if atom == 0 {
+        return String::new();
+    }
+
+    let name = xlib::XGetAtomName(display, atom);
+    if name.is_null() {
+        return String::new();
+    }
+
+    let string = CStr::from_ptr(name).to_string_lossy().into_owned();
+    xlib::XFree(name as *mut std::ffi::c_void);
+    string
+}
+
+/// Read the whole value of a window property, `None` if the window does not
+/// have it or does not exist
+pub(super) unsafe fn get_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    property: xlib::Atom,
+) -> Option<Property> {
+    let (mut type_, mut format, mut items, mut bytes_after) = (0, 0, 0, 0);
+    let mut data: *mut c_uchar = ptr::null_mut();
+
+    let (status, error_code) = xerror::trap_errors(display, || {
+        xlib::XGetWindowProperty(
+            display,
+            window,
+            property,
+            0,
+            c_long::MAX / 4,
+            xlib::False,
+            xlib::AnyPropertyType as xlib::Atom,
+            &mut type_,
+            &mut format,
+            &mut items,
+            &mut bytes_after,
+            &mut data,
+        )
+    });
+
+    if status != xlib::Success as c_int || error_code.is_some() || data.is_null() {
+        return None;
+    }
+    if type_ == 0 {
+        // The window does not have the property
+        xlib::XFree(data as *mut std::ffi::c_void);
+        return None;
+    }
+
+    let item_size = match format {
+        8 => 1,
+        16 => std::mem::size_of::<std::os::raw::c_short>(),
+        _ => std::mem::size_of::<c_long>(),
+    };
+    let value = slice::from_raw_parts(data, items as usize * item_size).to_vec();
+    xlib::XFree(data as *mut std::ffi::c_void);
+
+    Some(Property {
+        format,
+        data: value,
+    })
+}
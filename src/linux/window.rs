@@ -0,0 +1,215 @@
+//! Finding windows, their geometry and what they show
+
+use super::{property, xerror};
+use std::{collections::VecDeque, mem, ptr, slice};
+use x11::xlib;
+
+/// Get where a window is on its screen, `None` if it does not exist
+pub(super) unsafe fn geometry(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> Option<crate::Window> {
+    let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+    let (mut x, mut y, mut child) = (0, 0, 0);
+
+    let (found, error_code) = xerror::trap_errors(display, || {
+        xlib::XGetWindowAttributes(display, window, &mut attributes) != 0
+            && xlib::XTranslateCoordinates(
+                display,
+                window,
+                attributes.root,
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            ) != 0
+    });
+    if !found || error_code.is_some() {
+        return None;
+    }
+
+    // XIDs are c_ulong, which is only 32 bits wide on some targets
+    #[allow(clippy::unnecessary_cast)]
+    let id = window as u64;
+
+    Some(crate::Window::from(
+        id,
+        (x, y),
+        (
+            attributes.width.max(0) as u32,
+            attributes.height.max(0) as u32,
+        ),
+    ))
+}
+
+/// Find the application window inside a window, which is the frame the window
+/// manager wraps it in for top-level windows. This is the first window with a
+/// `WM_STATE` property, the window itself if none has it.
+pub(super) unsafe fn client_window(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> xlib::Window {
+    let wm_state = property::intern_atom(display, "WM_STATE");
+    let mut candidates = VecDeque::from(vec![window]);
+
+    while let Some(candidate) = candidates.pop_front() {
+        if property::get_property(display, candidate, wm_state).is_some() {
+            return candidate;
+        }
+        candidates.extend(children(display, candidate));
+    }
+
+    window
+}
+
+/// Get the top-level window that has the focus: the active window the window manager
+/// announces on the root window, or the window holding the X input focus if no
+/// window manager does. `None` when no window has the focus.
+pub(super) unsafe fn focused_window(
+    display: *mut xlib::Display,
+    root: xlib::Window,
+) -> Option<xlib::Window> {
+    let net_active_window = property::intern_atom(display, "_NET_ACTIVE_WINDOW");
+    if let Some(active) = property::get_property(display, root, net_active_window)
+        .and_then(|property| property.longs())
+    {
+        return active
+            .first()
+            .map(|window| *window as xlib::Window)
+            .filter(|window| *window != 0);
+    }
+
+    let (mut focus, mut revert_to) = (0, 0);
+    xlib::XGetInputFocus(display, &mut focus, &mut revert_to);
+    // `None` and `PointerRoot` are not windows
+    if focus == 0 || focus == xlib::PointerRoot as xlib::Window || focus == root {
+        return None;
+    }
+
+    Some(top_level_window(display, focus))
+}
+
+/// Describe a window with its title, class and process id, `None` if it does not exist
+pub(super) unsafe fn window_info(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> Option<crate::FocusedWindow> {
+    let string = |name: &str| {
+        property::get_property(display, window, property::intern_atom(display, name))
+            .and_then(|property| property.bytes().map(<[u8]>::to_vec))
+    };
+
+    // A window without attributes has been destroyed
+    let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+    let (found, error_code) = xerror::trap_errors(display, || {
+        xlib::XGetWindowAttributes(display, window, &mut attributes) != 0
+    });
+    if !found || error_code.is_some() {
+        return None;
+    }
+
+    // `_NET_WM_NAME` is UTF-8, the older `WM_NAME` is usually Latin-1 but read alike
+    let title = string("_NET_WM_NAME")
+        .or_else(|| string("WM_NAME"))
+        .map(|title| String::from_utf8_lossy(&title).into_owned())
+        .unwrap_or_default();
+    let class = string("WM_CLASS")
+        .map(|class| wm_class(&class))
+        .unwrap_or_default();
+    let pid = property::get_property(
+        display,
+        window,
+        property::intern_atom(display, "_NET_WM_PID"),
+    )
+    .and_then(|property| property.longs())
+    .and_then(|pid| pid.first().copied())
+    .map(|pid| pid as u32);
+
+    // XIDs are c_ulong, which is only 32 bits wide on some targets
+    #[allow(clippy::unnecessary_cast)]
+    let id = window as u64;
+
+    Some(crate::FocusedWindow::from(id, &title, &class, pid))
+}
+
+/// Get the class of a `WM_CLASS` value, which holds the instance and class names
+/// one after the other, each ended by a nul byte, such as `xterm\0XTerm\0`
+fn wm_class(value: &[u8]) -> String {
+    let mut names = value.split(|byte| *byte == 0);
+    let instance = names.next().unwrap_or_default();
+    let class = names.next().filter(|class| !class.is_empty());
+
+    String::from_utf8_lossy(class.unwrap_or(instance)).into_owned()
+}
+
+/// Find the top-level window holding a window: the first ancestor with a `WM_STATE`
+/// property, else the child of the root window it is in
+unsafe fn top_level_window(display: *mut xlib::Display, window: xlib::Window) -> xlib::Window {
+    let wm_state = property::intern_atom(display, "WM_STATE");
+    let mut candidate = window;
+
+    loop {
+        if property::get_property(display, candidate, wm_state).is_some() {
+            return candidate;
+        }
+
+        match query_tree(display, candidate) {
+            Some((root, parent, _)) if parent != 0 && parent != root => candidate = parent,
+            _ => return candidate,
+        }
+    }
+}
+
+/// Get the children of a window, empty if it does not exist
+unsafe fn children(display: *mut xlib::Display, window: xlib::Window) -> Vec<xlib::Window> {
+    query_tree(display, window)
+        .map(|(_, _, children)| children)
+        .unwrap_or_default()
+}
+
+/// Get the root, parent and children of a window, `None` if it does not exist
+unsafe fn query_tree(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> Option<(xlib::Window, xlib::Window, Vec<xlib::Window>)> {
+    let (mut root, mut parent, mut children, mut count) = (0, 0, ptr::null_mut(), 0);
+
+    let (status, error_code) = xerror::trap_errors(display, || {
+        xlib::XQueryTree(
+            display,
+            window,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut count,
+        )
+    });
+
+    let list = if children.is_null() {
+        Vec::new()
+    } else {
+        let list = slice::from_raw_parts(children, count as usize).to_vec();
+        xlib::XFree(children as *mut std::ffi::c_void);
+        list
+    };
+
+    if status != 0 && error_code.is_none() {
+        Some((root, parent, list))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wm_class_test() {
+        assert_eq!(wm_class(b"xterm\0XTerm\0"), "XTerm");
+        assert_eq!(wm_class(b"navigator\0Firefox"), "Firefox");
+        assert_eq!(wm_class(b"xclock\0"), "xclock");
+        assert_eq!(wm_class(b""), "");
+    }
+}
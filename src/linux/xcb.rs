@@ -0,0 +1,285 @@
+//! Sending several requests before waiting for their replies through the XCB
+//! connection underneath Xlib, whose libraries are loaded at runtime. Xlib itself
+//! waits for each reply before sending the next request.
+
+use super::{
+    devicestate::{core_buttons, Pointer},
+    library::Library,
+};
+use std::{
+    ffi::c_void,
+    os::raw::{c_char, c_int, c_uint},
+    ptr, slice,
+};
+use x11::xlib;
+
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+/// Identifies the reply to a request
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Cookie {
+    sequence: c_uint,
+}
+
+#[repr(C)]
+struct QueryKeymapReply {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    length: u32,
+    keys: [u8; 32],
+}
+
+#[repr(C)]
+struct QueryPointerReply {
+    response_type: u8,
+    same_screen: u8,
+    sequence: u16,
+    length: u32,
+    root: u32,
+    child: u32,
+    root_x: i16,
+    root_y: i16,
+    win_x: i16,
+    win_y: i16,
+    mask: u16,
+    pad0: [u8; 2],
+}
+
+#[repr(C)]
+struct ModifierInfo {
+    base: u32,
+    latched: u32,
+    locked: u32,
+    effective: u32,
+}
+
+#[repr(C)]
+struct GroupInfo {
+    base: u8,
+    latched: u8,
+    locked: u8,
+    effective: u8,
+}
+
+/// The reply to XIQueryPointer, followed by the button mask
+#[repr(C)]
+struct XiQueryPointerReply {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    length: u32,
+    root: u32,
+    child: u32,
+    /// Fixed point numbers with 16 bits of fraction
+    root_x: i32,
+    root_y: i32,
+    win_x: i32,
+    win_y: i32,
+    same_screen: u8,
+    pad1: u8,
+    buttons_len: u16,
+    mods: ModifierInfo,
+    group: GroupInfo,
+}
+
+type GetXcbConnection = unsafe extern "C" fn(*mut xlib::Display) -> *mut c_void;
+type QueryKeymap = unsafe extern "C" fn(*mut c_void) -> Cookie;
+type QueryKeymapReplyFn =
+    unsafe extern "C" fn(*mut c_void, Cookie, *mut *mut c_void) -> *mut QueryKeymapReply;
+type QueryPointer = unsafe extern "C" fn(*mut c_void, u32) -> Cookie;
+type QueryPointerReplyFn =
+    unsafe extern "C" fn(*mut c_void, Cookie, *mut *mut c_void) -> *mut QueryPointerReply;
+type XiQueryPointer = unsafe extern "C" fn(*mut c_void, u32, u16) -> Cookie;
+type XiQueryPointerReplyFn =
+    unsafe extern "C" fn(*mut c_void, Cookie, *mut *mut c_void) -> *mut XiQueryPointerReply;
+type XiQueryPointerButtons = unsafe extern "C" fn(*const XiQueryPointerReply) -> *mut u32;
+
+/// The XI2 pointer query of libxcb-xinput
+struct XcbInput {
+    query_pointer: XiQueryPointer,
+    query_pointer_reply: XiQueryPointerReplyFn,
+    query_pointer_buttons: XiQueryPointerButtons,
+    _library: Library,
+}
+
+/// The XCB functions, available when libX11-xcb and libxcb can be loaded
+pub(super) struct Xcb {
+    connection: *mut c_void,
+    query_keymap: QueryKeymap,
+    query_keymap_reply: QueryKeymapReplyFn,
+    query_pointer: QueryPointer,
+    query_pointer_reply: QueryPointerReplyFn,
+    input: Option<XcbInput>,
+    _xcb_library: Library,
+    _x11_xcb_library: Library,
+}
+
+impl Xcb {
+    /// Load libX11-xcb and libxcb and get the connection of the display, `None` if
+    /// either library is missing
+    pub(super) unsafe fn load(display: *mut xlib::Display) -> Option<Xcb> {
+        let x11_xcb_library = Library::open(&["libX11-xcb.so.1", "libX11-xcb.so"])?;
+        let xcb_library = Library::open(&["libxcb.so.1", "libxcb.so"])?;
+        let get_xcb_connection: GetXcbConnection = x11_xcb_library.function("XGetXCBConnection")?;
+
+        let connection = get_xcb_connection(display);
+        if connection.is_null() {
+            return None;
+        }
+
+        Some(Xcb {
+            connection,
+            query_keymap: xcb_library.function("xcb_query_keymap")?,
+            query_keymap_reply: xcb_library.function("xcb_query_keymap_reply")?,
+            query_pointer: xcb_library.function("xcb_query_pointer")?,
+            query_pointer_reply: xcb_library.function("xcb_query_pointer_reply")?,
+            input: XcbInput::load(),
+            _xcb_library: xcb_library,
+            _x11_xcb_library: x11_xcb_library,
+        })
+    }
+
+    /// Query the keymap bit vector and the pointer, sending both requests before
+    /// waiting for either reply. The pointer is queried with XI2 when `device` is
+    /// given. `None` if a request fails or XI2 is asked for without libxcb-xinput.
+    pub(super) unsafe fn query_state(
+        &self,
+        root: xlib::Window,
+        device: Option<c_int>,
+    ) -> Option<([c_char; 32], Pointer)> {
+        let input = match device {
+            Some(device) => Some((self.input.as_ref()?, device)),
+            None => None,
+        };
+
+        let keymap_cookie = (self.query_keymap)(self.connection);
+        let pointer = match input {
+            Some((input, device)) => {
+                let cookie = (input.query_pointer)(self.connection, root as u32, device as u16);
+                let keys = self.keymap_reply(keymap_cookie);
+                (keys, input.pointer_reply(self.connection, cookie))
+            }
+            None => {
+                let cookie = (self.query_pointer)(self.connection, root as u32);
+                let keys = self.keymap_reply(keymap_cookie);
+                (keys, self.pointer_reply(cookie))
+            }
+        };
+
+        match pointer {
+            (Some(keys), Some(pointer)) => Some((keys, pointer)),
+            _ => None,
+        }
+    }
+
+    /// Wait for the reply to a keymap query
+    unsafe fn keymap_reply(&self, cookie: Cookie) -> Option<[c_char; 32]> {
+        let mut error = ptr::null_mut();
+        let reply = take_reply((self.query_keymap_reply)(
+            self.connection,
+            cookie,
+            &mut error,
+        ));
+        free(error);
+        let reply = reply?;
+
+        let mut keys = [0; 32];
+        for (key, byte) in keys.iter_mut().zip(reply.keys.iter()) {
+            *key = *byte as c_char;
+        }
+        Some(keys)
+    }
+
+    /// Wait for the reply to a core pointer query
+    unsafe fn pointer_reply(&self, cookie: Cookie) -> Option<Pointer> {
+        let mut error = ptr::null_mut();
+        let reply = take_reply((self.query_pointer_reply)(
+            self.connection,
+            cookie,
+            &mut error,
+        ));
+        free(error);
+        let reply = reply?;
+
+        Some(Pointer {
+            coordinates: (i32::from(reply.root_x), i32::from(reply.root_y)),
+            buttons: core_buttons(c_uint::from(reply.mask)),
+            modifiers: c_uint::from(reply.mask),
+            same_screen: reply.same_screen != 0,
+            child: xlib::Window::from(reply.child),
+        })
+    }
+}
+
+impl XcbInput {
+    /// Load libxcb-xinput, `None` if it is missing
+    unsafe fn load() -> Option<XcbInput> {
+        let library = Library::open(&["libxcb-xinput.so.0", "libxcb-xinput.so"])?;
+
+        Some(XcbInput {
+            query_pointer: library.function("xcb_input_xi_query_pointer")?,
+            query_pointer_reply: library.function("xcb_input_xi_query_pointer_reply")?,
+            query_pointer_buttons: library.function("xcb_input_xi_query_pointer_buttons")?,
+            _library: library,
+        })
+    }
+
+    /// Wait for the reply to an XI2 pointer query
+    unsafe fn pointer_reply(&self, connection: *mut c_void, cookie: Cookie) -> Option<Pointer> {
+        let mut error = ptr::null_mut();
+        let reply = (self.query_pointer_reply)(connection, cookie, &mut error);
+        free(error);
+        if reply.is_null() {
+            return None;
+        }
+
+        let mask = slice::from_raw_parts(
+            (self.query_pointer_buttons)(reply),
+            usize::from((*reply).buttons_len),
+        );
+        // Bit 0 of the mask stands for no button
+        let buttons = (1..=u8::MAX)
+            .take_while(|button| (*button as usize) < mask.len() * 32)
+            .filter(|button| mask[*button as usize / 32] & (1 << (button % 32)) != 0)
+            .collect();
+        let reply = take_reply(reply)?;
+
+        Some(Pointer {
+            // Dropping the fraction, as the core protocol does
+            coordinates: (reply.root_x >> 16, reply.root_y >> 16),
+            buttons,
+            modifiers: reply.mods.effective as c_uint,
+            same_screen: reply.same_screen != 0,
+            child: xlib::Window::from(reply.child),
+        })
+    }
+}
+
+/// Copy a reply and free it, `None` if the request failed
+unsafe fn take_reply<T>(reply: *mut T) -> Option<T> {
+    if reply.is_null() {
+        return None;
+    }
+
+    let value = ptr::read(reply);
+    free(reply as *mut c_void);
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn reply_size_test() {
+        // The sizes of the xcb_*_reply_t structs of the XCB headers
+        assert_eq!(mem::size_of::<QueryKeymapReply>(), 40);
+        assert_eq!(mem::size_of::<QueryPointerReply>(), 28);
+        assert_eq!(mem::size_of::<XiQueryPointerReply>(), 56);
+    }
+}
@@ -0,0 +1,166 @@
+//! Catching the errors the X server reports for asynchronous requests
+
+use std::{
+    os::raw::{c_int, c_ulong},
+    sync::{Mutex, MutexGuard},
+};
+use x11::xlib;
+
+type Handler = unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XErrorEvent) -> c_int;
+
+/// The process wide error handler found when ours was installed, which receives
+/// the errors no trap expects, and how many ErrorHandlers keep ours installed
+struct Installed {
+    previous: Option<Handler>,
+    users: usize,
+}
+
+/// The requests whose errors are caught, by serial number
+struct Trap {
+    display: usize,
+    first: c_ulong,
+    last: c_ulong,
+    error_code: Option<u8>,
+}
+
+static INSTALLED: Mutex<Installed> = Mutex::new(Installed {
+    previous: None,
+    users: 0,
+});
+
+static TRAPS: Mutex<Vec<Trap>> = Mutex::new(Vec::new());
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+unsafe extern "C" fn handle_error(
+    display: *mut xlib::Display,
+    event: *mut xlib::XErrorEvent,
+) -> c_int {
+    let (serial, error_code) = ((*event).serial, (*event).error_code);
+    {
+        let mut traps = lock(&TRAPS);
+        let trap = traps.iter_mut().rev().find(|trap| {
+            trap.display == display as usize && trap.first <= serial && serial <= trap.last
+        });
+        if let Some(trap) = trap {
+            trap.error_code.get_or_insert(error_code);
+            return 0;
+        }
+    }
+
+    match lock(&INSTALLED).previous {
+        Some(previous) => previous(display, event),
+        None => 0,
+    }
+}
+
+/// Keeps the error handler `trap_errors()` relies on installed while it lives.
+/// The handler chains to the one installed before it for the errors of other requests.
+pub(super) struct ErrorHandler {
+    _private: (),
+}
+
+impl ErrorHandler {
+    /// Install the error handler, unless another ErrorHandler already did
+    pub(super) fn install() -> ErrorHandler {
+        let mut installed = lock(&INSTALLED);
+        if installed.users == 0 {
+            installed.previous = unsafe { xlib::XSetErrorHandler(Some(handle_error)) };
+        }
+        installed.users += 1;
+
+        ErrorHandler { _private: () }
+    }
+}
+
+impl Drop for ErrorHandler {
+    /// Restore the previous error handler once no ErrorHandler is left, unless the
+    /// application replaced ours in the meantime
+    fn drop(&mut self) {
+        let mut installed = lock(&INSTALLED);
+        installed.users -= 1;
+        if installed.users > 0 {
+            return;
+        }
+
+        unsafe {
+            let current = xlib::XSetErrorHandler(installed.previous.take());
+            if current.map(|handler| handler as usize) != Some(handle_error as Handler as usize) {
+                xlib::XSetErrorHandler(current);
+            }
+        }
+    }
+}
+
+/// Run `f` and return the code of the first error the requests it sent raised, if
+/// any, instead of letting Xlib abort the process. An ErrorHandler has to be alive.
+/// The server is only waited for when it has not answered the last request yet.
+pub(super) unsafe fn trap_errors<T>(
+    display: *mut xlib::Display,
+    f: impl FnOnce() -> T,
+) -> (T, Option<u8>) {
+    let first = xlib::XNextRequest(display);
+    lock(&TRAPS).push(Trap {
+        display: display as usize,
+        first,
+        last: c_ulong::MAX,
+        error_code: None,
+    });
+
+    let result = f();
+
+    let last = xlib::XNextRequest(display).wrapping_sub(1);
+    if let Some(trap) = lock(&TRAPS)
+        .iter_mut()
+        .rev()
+        .find(|trap| trap.display == display as usize && trap.first == first)
+    {
+        trap.last = last;
+    }
+    // The errors of the requests arrive before the replies of the later ones
+    if last >= first && xlib::XLastKnownRequestProcessed(display) < last {
+        xlib::XSync(display, xlib::False);
+    }
+
+    let mut traps = lock(&TRAPS);
+    let error_code = traps
+        .iter()
+        .rposition(|trap| trap.display == display as usize && trap.first == first)
+        .and_then(|ix| traps.remove(ix).error_code);
+
+    (result, error_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn handle_error_test() {
+        // Never dereferenced for trapped errors
+        let display = 0x1000 as *mut xlib::Display;
+        lock(&TRAPS).push(Trap {
+            display: display as usize,
+            first: 10,
+            last: 12,
+            error_code: None,
+        });
+
+        let mut event: xlib::XErrorEvent = unsafe { mem::zeroed() };
+        for (serial, error_code) in &[(11, xlib::BadWindow), (12, xlib::BadAccess)] {
+            event.serial = *serial;
+            event.error_code = *error_code;
+            assert_eq!(unsafe { handle_error(display, &mut event) }, 0);
+        }
+
+        let mut traps = lock(&TRAPS);
+        let ix = traps
+            .iter()
+            .position(|trap| trap.display == display as usize)
+            .unwrap();
+        assert_eq!(traps.remove(ix).error_code, Some(xlib::BadWindow));
+    }
+}
@@ -0,0 +1,254 @@
+//! Cursor visibility and images from the X Fixes extension, whose library is
+//! loaded at runtime
+
+use super::{library::Library, xerror};
+use crate::CursorImage;
+use std::{
+    ffi::{c_void, CStr},
+    os::raw::{c_int, c_ulong},
+    ptr, slice,
+};
+use x11::{xfixes, xlib};
+
+// XFixes constants missing from the x11 bindings
+const X_FIXES_CURSOR_NOTIFY: c_int = 1;
+const X_FIXES_DISPLAY_CURSOR_NOTIFY_MASK: c_ulong = 1;
+const BARRIER_POSITIVE_X: c_int = 1 << 0;
+const BARRIER_POSITIVE_Y: c_int = 1 << 1;
+const BARRIER_NEGATIVE_X: c_int = 1 << 2;
+const BARRIER_NEGATIVE_Y: c_int = 1 << 3;
+
+type QueryExtension =
+    unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> xlib::Bool;
+type QueryVersion =
+    unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> xlib::Status;
+type SelectCursorInput = unsafe extern "C" fn(*mut xlib::Display, xlib::Window, c_ulong);
+type GetCursorImage = unsafe extern "C" fn(*mut xlib::Display) -> *mut xfixes::XFixesCursorImage;
+type CursorVisibility = unsafe extern "C" fn(*mut xlib::Display, xlib::Window);
+type CreatePointerBarrier = unsafe extern "C" fn(
+    *mut xlib::Display,
+    xlib::Window,
+    c_int,
+    c_int,
+    c_int,
+    c_int,
+    c_int,
+    c_int,
+    *mut c_int,
+) -> xlib::XID;
+type DestroyPointerBarrier = unsafe extern "C" fn(*mut xlib::Display, xlib::XID);
+
+/// A barrier the pointer cannot cross, from `(x1, y1)` to `(x2, y2)`, and the
+/// directions it lets the pointer through
+type BarrierLine = (c_int, c_int, c_int, c_int, c_int);
+
+/// A change of the cursor reported by the server
+pub(super) struct CursorChange {
+    pub(super) serial: u64,
+    pub(super) name: xlib::Atom,
+    pub(super) time: xlib::Time,
+}
+
+/// The XFixes functions, available when both the library and the server support
+/// XFixes 4.0, which introduced hiding the cursor
+pub(super) struct XFixes {
+    event_base: c_int,
+    get_cursor_image: GetCursorImage,
+    hide_cursor: CursorVisibility,
+    show_cursor: CursorVisibility,
+    /// Available with XFixes 5.0
+    pointer_barriers: Option<(CreatePointerBarrier, DestroyPointerBarrier)>,
+    _library: Library,
+}
+
+impl XFixes {
+    /// Load libXfixes and ask the server to report cursor changes on the root window,
+    /// `None` if either lacks XFixes 4.0
+    pub(super) unsafe fn load(display: *mut xlib::Display, root: xlib::Window) -> Option<XFixes> {
+        let library = Library::open(&["libXfixes.so.3", "libXfixes.so"])?;
+        let query_extension: QueryExtension = library.function("XFixesQueryExtension")?;
+        let query_version: QueryVersion = library.function("XFixesQueryVersion")?;
+        let select_cursor_input: SelectCursorInput = library.function("XFixesSelectCursorInput")?;
+
+        let (mut event_base, mut error_base) = (0, 0);
+        if query_extension(display, &mut event_base, &mut error_base) == 0 {
+            return None;
+        }
+
+        // The server answers with the highest version both sides support
+        let (mut major, mut minor) = (5, 0);
+        if query_version(display, &mut major, &mut minor) == 0 || major < 4 {
+            return None;
+        }
+
+        select_cursor_input(display, root, X_FIXES_DISPLAY_CURSOR_NOTIFY_MASK);
+        let pointer_barriers = if major >= 5 {
+            library
+                .function("XFixesCreatePointerBarrier")
+                .zip(library.function("XFixesDestroyPointerBarrier"))
+        } else {
+            None
+        };
+
+        Some(XFixes {
+            event_base,
+            get_cursor_image: library.function("XFixesGetCursorImage")?,
+            hide_cursor: library.function("XFixesHideCursor")?,
+            show_cursor: library.function("XFixesShowCursor")?,
+            pointer_barriers,
+            _library: library,
+        })
+    }
+
+    /// Get the change of the cursor an event reports, `None` for other events
+    pub(super) unsafe fn process_event(&self, event: &xlib::XEvent) -> Option<CursorChange> {
+        if event.get_type() != self.event_base + X_FIXES_CURSOR_NOTIFY {
+            return None;
+        }
+
+        let event = &*(event as *const xlib::XEvent as *const xfixes::XFixesCursorNotifyEvent);
+        // Serials are c_ulong, which is only 32 bits wide on some targets
+        #[allow(clippy::useless_conversion)]
+        let serial = u64::from(event.cursor_serial);
+
+        Some(CursorChange {
+            serial,
+            name: event.cursor_name,
+            time: event.timestamp,
+        })
+    }
+
+    /// Hide the cursor on every window of a screen, until `show_cursor()` is called or
+    /// the connection is closed
+    pub(super) unsafe fn hide_cursor(&self, display: *mut xlib::Display, root: xlib::Window) {
+        (self.hide_cursor)(display, root);
+    }
+
+    /// Show the cursor hidden with `hide_cursor()` again
+    pub(super) unsafe fn show_cursor(&self, display: *mut xlib::Display, root: xlib::Window) {
+        (self.show_cursor)(display, root);
+    }
+
+    /// Surround a region of the screen with pointer barriers, which keep the pointer
+    /// of every device from leaving it. `None` without XFixes 5.0, `Some(Err(()))` if
+    /// the server refuses the region.
+    pub(super) unsafe fn create_barriers(
+        &self,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+        position: (i32, i32),
+        size: (u32, u32),
+    ) -> Option<Result<Vec<xlib::XID>, ()>> {
+        let (create_pointer_barrier, _) = self.pointer_barriers?;
+
+        let (barriers, error_code) = xerror::trap_errors(display, || {
+            barrier_lines(position, size)
+                .iter()
+                .map(|(x1, y1, x2, y2, directions)| {
+                    create_pointer_barrier(
+                        display,
+                        root,
+                        *x1,
+                        *y1,
+                        *x2,
+                        *y2,
+                        *directions,
+                        0,
+                        ptr::null_mut(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+
+        if error_code.is_some() {
+            self.destroy_barriers(display, &barriers);
+            return Some(Err(()));
+        }
+        Some(Ok(barriers))
+    }
+
+    /// Remove the barriers made by `create_barriers()`
+    pub(super) unsafe fn destroy_barriers(
+        &self,
+        display: *mut xlib::Display,
+        barriers: &[xlib::XID],
+    ) {
+        if let Some((_, destroy_pointer_barrier)) = self.pointer_barriers {
+            // Some of the barriers may not exist if creating them failed
+            xerror::trap_errors(display, || {
+                for barrier in barriers.iter().filter(|barrier| **barrier != 0) {
+                    destroy_pointer_barrier(display, *barrier);
+                }
+            });
+        }
+    }
+
+    /// Get the picture of the cursor, `None` if the server cannot send it
+    pub(super) unsafe fn cursor_image(&self, display: *mut xlib::Display) -> Option<CursorImage> {
+        let image = (self.get_cursor_image)(display);
+        if image.is_null() {
+            return None;
+        }
+
+        let info = &*image;
+        let size = (u32::from(info.width), u32::from(info.height));
+        // Xlib stores each 32-bit ARGB pixel in a long, which is 32 bits wide on some targets
+        #[allow(clippy::unnecessary_cast)]
+        let pixels = slice::from_raw_parts(info.pixels, size.0 as usize * size.1 as usize)
+            .iter()
+            .map(|pixel| *pixel as u32)
+            .collect();
+        #[allow(clippy::useless_conversion)]
+        let serial = u64::from(info.cursor_serial);
+        let name = if info.name.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(info.name).to_string_lossy().into_owned()
+        };
+
+        let cursor = CursorImage::from(
+            size,
+            (u32::from(info.xhot), u32::from(info.yhot)),
+            serial,
+            &name,
+            pixels,
+        );
+        xlib::XFree(image as *mut c_void);
+
+        Some(cursor)
+    }
+}
+
+/// Get the barriers along the edges of a region, each letting the pointer into it.
+/// A barrier stops the pointer on the near side when crossed against its directions,
+/// so the right and bottom ones lie just past the region.
+fn barrier_lines(position: (i32, i32), size: (u32, u32)) -> [BarrierLine; 4] {
+    let (left, top) = position;
+    let right = left.saturating_add(size.0.min(i32::MAX as u32) as i32);
+    let bottom = top.saturating_add(size.1.min(i32::MAX as u32) as i32);
+
+    [
+        (left, top, left, bottom, BARRIER_POSITIVE_X),
+        (right, top, right, bottom, BARRIER_NEGATIVE_X),
+        (left, top, right, top, BARRIER_POSITIVE_Y),
+        (left, bottom, right, bottom, BARRIER_NEGATIVE_Y),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barrier_lines_test() {
+        assert_eq!(
+            barrier_lines((100, 50), (200, 100)),
+            [
+                (100, 50, 100, 150, BARRIER_POSITIVE_X),
+                (300, 50, 300, 150, BARRIER_NEGATIVE_X),
+                (100, 50, 300, 50, BARRIER_POSITIVE_Y),
+                (100, 150, 300, 150, BARRIER_NEGATIVE_Y),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1299 @@
+//! Raw pointer events and device details from the X Input Extension 2, whose
+//! library is loaded at runtime
+
+use super::{devicestate::Pointer, library::Library, property, xerror};
+use crate::{DeviceKind, InputDevice, MotionDelta, PenState, PenTool, ScrollDelta, TouchPoint};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_double, c_int, c_long, c_short, c_uchar, c_uint, c_ulong},
+    ptr, slice,
+};
+use x11::{xinput as xi1, xinput2 as xi, xlib};
+
+// XInput 1 state classes, missing from the x11 bindings
+const KEY_CLASS: c_uchar = 0;
+const BUTTON_CLASS: c_uchar = 1;
+const VALUATOR_CLASS: c_uchar = 2;
+const OUT_OF_PROXIMITY: c_uchar = 1 << 1;
+
+#[repr(C)]
+struct XKeyState {
+    class: c_uchar,
+    length: c_uchar,
+    num_keys: c_short,
+    keys: [c_char; 32],
+}
+
+#[repr(C)]
+struct XButtonState {
+    class: c_uchar,
+    length: c_uchar,
+    num_buttons: c_short,
+    buttons: [c_char; 32],
+}
+
+#[repr(C)]
+struct XValuatorState {
+    class: c_uchar,
+    length: c_uchar,
+    num_valuators: c_uchar,
+    mode: c_uchar,
+    valuators: *mut c_int,
+}
+
+type QueryVersion = unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> c_int;
+type SelectEvents =
+    unsafe extern "C" fn(*mut xlib::Display, xlib::Window, *mut xi::XIEventMask, c_int) -> c_int;
+type QueryDevice =
+    unsafe extern "C" fn(*mut xlib::Display, c_int, *mut c_int) -> *mut xi::XIDeviceInfo;
+type FreeDeviceInfo = unsafe extern "C" fn(*mut xi::XIDeviceInfo);
+type GetClientPointer =
+    unsafe extern "C" fn(*mut xlib::Display, xlib::Window, *mut c_int) -> xlib::Bool;
+type QueryPointer = unsafe extern "C" fn(
+    *mut xlib::Display,
+    c_int,
+    xlib::Window,
+    *mut xlib::Window,
+    *mut xlib::Window,
+    *mut c_double,
+    *mut c_double,
+    *mut c_double,
+    *mut c_double,
+    *mut xi::XIButtonState,
+    *mut xi::XIModifierState,
+    *mut xi::XIGroupState,
+) -> xlib::Bool;
+type OpenDevice = unsafe extern "C" fn(*mut xlib::Display, xlib::XID) -> *mut xi1::XDevice;
+type QueryDeviceState =
+    unsafe extern "C" fn(*mut xlib::Display, *mut xi1::XDevice) -> *mut xi1::XDeviceState;
+type FreeDeviceState = unsafe extern "C" fn(*mut xi1::XDeviceState);
+type CloseDevice = unsafe extern "C" fn(*mut xlib::Display, *mut xi1::XDevice) -> c_int;
+type GetProperty = unsafe extern "C" fn(
+    *mut xlib::Display,
+    c_int,
+    xlib::Atom,
+    c_long,
+    c_long,
+    xlib::Bool,
+    xlib::Atom,
+    *mut xlib::Atom,
+    *mut c_int,
+    *mut c_ulong,
+    *mut c_ulong,
+    *mut *mut c_uchar,
+) -> c_int;
+
+/// The coordinate transformation matrix of devices without one, row by row
+const IDENTITY_MATRIX: [f64; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+/// A scrolling axis of a device
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(super) struct ScrollValuator {
+    number: c_int,
+    horizontal: bool,
+    /// The distance along the axis matching one wheel notch
+    increment: f64,
+}
+
+/// An axis of a device and the range of its values
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct ValuatorRange {
+    number: c_int,
+    minimum: f64,
+    maximum: f64,
+}
+
+impl ValuatorRange {
+    /// Scale a value of the axis to 0.0..=1.0
+    fn fraction(&self, value: f64) -> f64 {
+        if self.maximum <= self.minimum {
+            return 0.0;
+        }
+        ((value - self.minimum) / (self.maximum - self.minimum)).clamp(0.0, 1.0)
+    }
+
+    /// Scale a value of an axis centered on 0 to -1.0..=1.0
+    fn signed_fraction(&self, value: f64) -> f64 {
+        let extent = self.minimum.abs().max(self.maximum.abs());
+        if extent == 0.0 {
+            return 0.0;
+        }
+        (value / extent).clamp(-1.0, 1.0)
+    }
+
+    /// Scale a distance along the axis to a distance along a screen dimension the
+    /// whole axis is mapped to
+    fn screen_distance(&self, distance: f64, screen: u32) -> f64 {
+        if self.maximum <= self.minimum {
+            return 0.0;
+        }
+        distance / (self.maximum - self.minimum) * f64::from(screen)
+    }
+}
+
+/// The axes of a touchscreen a touch is read from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(super) struct TouchValuators {
+    x: ValuatorRange,
+    y: ValuatorRange,
+    pressure: Option<ValuatorRange>,
+    touch_major: Option<ValuatorRange>,
+    touch_minor: Option<ValuatorRange>,
+    /// The coordinate transformation matrix mapping the touchscreen, as fractions
+    /// of its axes, to fractions of the screen, row by row
+    matrix: [f64; 9],
+}
+
+/// A touch followed through raw events
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(super) struct RawTouch {
+    pub(super) point: TouchPoint,
+    /// Where the touch is as fractions of the touchscreen axes, before the
+    /// coordinate transformation matrix
+    fractions: (f64, f64),
+}
+
+/// The axes of a tablet pen
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct PenValuators {
+    x: ValuatorRange,
+    y: ValuatorRange,
+    pressure: Option<ValuatorRange>,
+    tilt_x: Option<ValuatorRange>,
+    tilt_y: Option<ValuatorRange>,
+}
+
+/// The kinds of raw events followed, selected on the first query needing them as
+/// the server sends raw motion for every report of the mouse
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(super) struct RawEvents {
+    pub(super) motion: bool,
+    pub(super) scroll: bool,
+    pub(super) touch: bool,
+}
+
+/// The keys and buttons held on a physical device
+pub(super) struct HeldInputs {
+    /// The keymap bit vector, as in `XQueryKeymap()`
+    pub(super) keys: [c_char; 32],
+    /// The numbers of the held buttons, before the core pointer mapping
+    pub(super) buttons: Vec<u8>,
+    /// Whether a tablet tool is close enough to be followed, always true for
+    /// other devices
+    pub(super) in_proximity: bool,
+}
+
+/// The XI2 functions, available when both the library and the server support XI 2.1.
+/// Touch devices are only described to clients announcing XI 2.2.
+pub(super) struct XInput {
+    opcode: c_int,
+    /// Whether the server supports XI 2.2, which introduced touch events
+    touch: bool,
+    select_events: SelectEvents,
+    query_device: QueryDevice,
+    free_device_info: FreeDeviceInfo,
+    get_client_pointer: GetClientPointer,
+    client_pointer: Cell<Option<c_int>>,
+    query_pointer: QueryPointer,
+    open_device: OpenDevice,
+    query_device_state: QueryDeviceState,
+    free_device_state: FreeDeviceState,
+    close_device: CloseDevice,
+    /// The XInput 1 handles of the physical devices, kept open across queries
+    open_devices: RefCell<HashMap<xlib::XID, *mut xi1::XDevice>>,
+    get_property: GetProperty,
+    _library: Library,
+}
+
+impl XInput {
+    /// Load libXi and announce XI 2.2 support to the server, `None` if either
+    /// lacks XI 2.1
+    pub(super) unsafe fn load(display: *mut xlib::Display) -> Option<XInput> {
+        let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+        let name = CString::new("XInputExtension").expect("No nul bytes in the extension name");
+        if xlib::XQueryExtension(
+            display,
+            name.as_ptr(),
+            &mut opcode,
+            &mut event_base,
+            &mut error_base,
+        ) == 0
+        {
+            return None;
+        }
+
+        let library = Library::open(&["libXi.so.6", "libXi.so"])?;
+        let query_version: QueryVersion = library.function("XIQueryVersion")?;
+
+        // The server answers with the highest version both sides support
+        let (mut major, mut minor) = (2, 2);
+        if query_version(display, &mut major, &mut minor) != xlib::Success as c_int
+            || (major, minor) < (2, 1)
+        {
+            return None;
+        }
+
+        Some(XInput {
+            opcode,
+            touch: minor >= 2,
+            select_events: library.function("XISelectEvents")?,
+            query_device: library.function("XIQueryDevice")?,
+            free_device_info: library.function("XIFreeDeviceInfo")?,
+            get_client_pointer: library.function("XIGetClientPointer")?,
+            client_pointer: Cell::new(None),
+            query_pointer: library.function("XIQueryPointer")?,
+            open_device: library.function("XOpenDevice")?,
+            query_device_state: library.function("XQueryDeviceState")?,
+            free_device_state: library.function("XFreeDeviceState")?,
+            close_device: library.function("XCloseDevice")?,
+            open_devices: RefCell::new(HashMap::new()),
+            get_property: library.function("XIGetProperty")?,
+            _library: library,
+        })
+    }
+
+    /// Get the major opcode identifying the extension's generic events
+    pub(super) fn opcode(&self) -> c_int {
+        self.opcode
+    }
+
+    /// Ask for the device changes, and the property changes that may replace the
+    /// coordinate transformation matrix of a touchscreen
+    pub(super) unsafe fn select_device_events(
+        &self,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+    ) {
+        let mut device_mask = [0; (xi::XI_LASTEVENT as usize >> 3) + 1];
+        xi::XISetMask(&mut device_mask, xi::XI_HierarchyChanged);
+        xi::XISetMask(&mut device_mask, xi::XI_DeviceChanged);
+        xi::XISetMask(&mut device_mask, xi::XI_PropertyEvent);
+
+        let mut mask = event_mask(xi::XIAllDevices, &mut device_mask);
+        (self.select_events)(display, root, &mut mask, 1);
+    }
+
+    /// Ask for the raw events of the master devices, which are delivered whatever
+    /// window has the focus or grabbed the pointer, replacing the previous selection.
+    /// Smooth scrolling is reported by raw motion, wheel notches by raw button presses.
+    pub(super) unsafe fn select_raw_events(
+        &self,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+        raw_events: RawEvents,
+    ) {
+        let mut raw_mask = [0; (xi::XI_LASTEVENT as usize >> 3) + 1];
+        if raw_events.scroll {
+            xi::XISetMask(&mut raw_mask, xi::XI_RawButtonPress);
+        }
+        if raw_events.motion || raw_events.scroll {
+            xi::XISetMask(&mut raw_mask, xi::XI_RawMotion);
+        }
+        if raw_events.touch && self.touch {
+            xi::XISetMask(&mut raw_mask, xi::XI_RawTouchBegin);
+            xi::XISetMask(&mut raw_mask, xi::XI_RawTouchUpdate);
+            xi::XISetMask(&mut raw_mask, xi::XI_RawTouchEnd);
+        }
+
+        let mut mask = event_mask(xi::XIAllMasterDevices, &mut raw_mask);
+        (self.select_events)(display, root, &mut mask, 1);
+    }
+
+    /// Get the scrolling axes of the devices that have some, by device id
+    pub(super) unsafe fn scroll_valuators(
+        &self,
+        display: *mut xlib::Display,
+    ) -> HashMap<c_int, Vec<ScrollValuator>> {
+        let mut valuators = HashMap::new();
+        let mut count = 0;
+        let devices = (self.query_device)(display, xi::XIAllDevices, &mut count);
+        if devices.is_null() {
+            return valuators;
+        }
+
+        for device in slice::from_raw_parts(devices, count as usize) {
+            let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+            let scroll: Vec<ScrollValuator> = classes
+                .iter()
+                .filter(|class| (***class)._type == xi::XIScrollClass)
+                .map(|class| {
+                    let class = &*(*class as *const xi::XIScrollClassInfo);
+                    ScrollValuator {
+                        number: class.number,
+                        horizontal: class.scroll_type == xi::XIScrollTypeHorizontal,
+                        increment: class.increment,
+                    }
+                })
+                .collect();
+
+            if !scroll.is_empty() {
+                valuators.insert(device.deviceid, scroll);
+            }
+        }
+
+        (self.free_device_info)(devices);
+        valuators
+    }
+
+    /// Get the axes of the touchscreens, by device id. Touchpads are left out, as
+    /// their touches are not positions on the screen.
+    pub(super) unsafe fn touch_valuators(
+        &self,
+        display: *mut xlib::Display,
+    ) -> HashMap<c_int, TouchValuators> {
+        let mut valuators = HashMap::new();
+        let mut count = 0;
+        let devices = (self.query_device)(display, xi::XIAllDevices, &mut count);
+        if devices.is_null() {
+            return valuators;
+        }
+
+        let labels = [
+            "Abs MT Position X",
+            "Abs MT Position Y",
+            "Abs MT Pressure",
+            "Abs MT Touch Major",
+            "Abs MT Touch Minor",
+        ]
+        .map(|label| property::intern_atom(display, label));
+
+        for device in slice::from_raw_parts(devices, count as usize) {
+            let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+            let direct_touch = classes.iter().any(|class| {
+                (**class)._type == xi::XITouchClass
+                    && (*(*class as *const xi::XITouchClassInfo)).mode == xi::XIDirectTouch
+            });
+            if !direct_touch {
+                continue;
+            }
+
+            let ranges = valuator_ranges(classes);
+            let axis = |label: usize| labelled_range(&ranges, labels[label]);
+            // Drivers that do not label the axes put the position first
+            let x = axis(0).or_else(|| numbered_range(&ranges, 0));
+            let y = axis(1).or_else(|| numbered_range(&ranges, 1));
+
+            if let (Some(x), Some(y)) = (x, y) {
+                valuators.insert(
+                    device.deviceid,
+                    TouchValuators {
+                        x,
+                        y,
+                        pressure: axis(2),
+                        touch_major: axis(3),
+                        touch_minor: axis(4),
+                        matrix: self.transformation_matrix(display, device.deviceid),
+                    },
+                );
+            }
+        }
+
+        (self.free_device_info)(devices);
+        valuators
+    }
+
+    /// Get the coordinate transformation matrix of a device, which maps touchscreens
+    /// to their monitor, the identity if it has none
+    unsafe fn transformation_matrix(&self, display: *mut xlib::Display, device: c_int) -> [f64; 9] {
+        let property = property::intern_atom(display, "Coordinate Transformation Matrix");
+        let float = property::intern_atom(display, "FLOAT");
+        let (mut kind, mut format, mut count, mut remaining) = (0, 0, 0, 0);
+        let mut data = ptr::null_mut();
+        let status = (self.get_property)(
+            display,
+            device,
+            property,
+            0,
+            9,
+            xlib::False,
+            xlib::AnyPropertyType as xlib::Atom,
+            &mut kind,
+            &mut format,
+            &mut count,
+            &mut remaining,
+            &mut data,
+        );
+        if status != xlib::Success as c_int || data.is_null() {
+            return IDENTITY_MATRIX;
+        }
+
+        // Unlike XGetWindowProperty(), the 32-bit items are not widened to longs
+        let mut matrix = IDENTITY_MATRIX;
+        if kind == float && format == 32 && count == 9 {
+            let floats = slice::from_raw_parts(data as *const f32, 9);
+            for (cell, value) in matrix.iter_mut().zip(floats) {
+                *cell = f64::from(*value);
+            }
+        }
+        xlib::XFree(data as *mut _);
+        matrix
+    }
+
+    /// Read the pens of the graphics tablets on a screen of this size. The position
+    /// and axes come from XI2, the buttons and proximity from XInput 1.
+    pub(super) unsafe fn pens(
+        &self,
+        display: *mut xlib::Display,
+        screen: (u32, u32),
+    ) -> Vec<PenState> {
+        let mut count = 0;
+        let devices = (self.query_device)(display, xi::XIAllDevices, &mut count);
+        if devices.is_null() {
+            return Vec::new();
+        }
+
+        let labels = ["Abs X", "Abs Y", "Abs Pressure", "Abs Tilt X", "Abs Tilt Y"]
+            .map(|label| property::intern_atom(display, label));
+
+        let mut found = Vec::new();
+        for device in slice::from_raw_parts(devices, count as usize) {
+            let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+            let touch = classes
+                .iter()
+                .any(|class| (**class)._type == xi::XITouchClass);
+            if device._use == xi::XIMasterPointer || device._use == xi::XIMasterKeyboard || touch {
+                continue;
+            }
+
+            let ranges = valuator_ranges(classes);
+            let axis = |label: usize| labelled_range(&ranges, labels[label]);
+            let x = axis(0).or_else(|| numbered_range(&ranges, 0));
+            let y = axis(1).or_else(|| numbered_range(&ranges, 1));
+            let (x, y, pressure) = match (x, y, axis(2)) {
+                (Some(x), Some(y), Some(pressure)) => (x, y, pressure),
+                _ => continue,
+            };
+
+            let valuators = PenValuators {
+                x,
+                y,
+                pressure: Some(pressure),
+                tilt_x: axis(3),
+                tilt_y: axis(4),
+            };
+            let values: Vec<(c_int, f64)> = ranges
+                .iter()
+                .map(|(_, range, value)| (range.number, *value))
+                .collect();
+            let name = CStr::from_ptr(device.name).to_string_lossy().into_owned();
+            found.push((device.deviceid, name, valuators, values));
+        }
+        (self.free_device_info)(devices);
+
+        found
+            .into_iter()
+            .map(|(id, name, valuators, values)| {
+                let held = self.held_inputs(display, id as xlib::XID);
+                pen_state(id as u64, &name, &values, &valuators, screen, held.as_ref())
+            })
+            .collect()
+    }
+
+    /// List the master devices and the physical devices
+    pub(super) unsafe fn devices(&self, display: *mut xlib::Display) -> Vec<InputDevice> {
+        let mut count = 0;
+        let devices = (self.query_device)(display, xi::XIAllDevices, &mut count);
+        if devices.is_null() {
+            return Vec::new();
+        }
+
+        let abs_pressure = property::intern_atom(display, "Abs Pressure");
+        let list = slice::from_raw_parts(devices, count as usize)
+            .iter()
+            .map(|device| {
+                let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+                let has_class = |kind| classes.iter().any(|class| (**class)._type == kind);
+                let direct_touch = classes.iter().any(|class| {
+                    (**class)._type == xi::XITouchClass
+                        && (*(*class as *const xi::XITouchClassInfo)).mode == xi::XIDirectTouch
+                });
+                let pressure = classes.iter().any(|class| {
+                    (**class)._type == xi::XIValuatorClass
+                        && (*(*class as *const xi::XIValuatorClassInfo)).label == abs_pressure
+                });
+
+                let kind = device_kind(
+                    device._use,
+                    has_class(xi::XIKeyClass),
+                    has_class(xi::XIButtonClass),
+                    direct_touch,
+                    pressure,
+                );
+                let is_slave =
+                    device._use == xi::XISlavePointer || device._use == xi::XISlaveKeyboard;
+
+                InputDevice::from(
+                    device.deviceid as u64,
+                    &CStr::from_ptr(device.name).to_string_lossy(),
+                    kind,
+                    device.enabled != 0,
+                    device._use == xi::XIMasterPointer || device._use == xi::XIMasterKeyboard,
+                    Some(device.attachment as u64).filter(|_| is_slave),
+                )
+            })
+            .collect();
+
+        (self.free_device_info)(devices);
+        list
+    }
+
+    /// Get the keys and buttons held on a physical device, `None` if there is no such
+    /// device or it is a master device. XI2 cannot tell, so this goes through XInput 1,
+    /// which only knows about physical devices.
+    pub(super) unsafe fn held_inputs(
+        &self,
+        display: *mut xlib::Display,
+        device_id: xlib::XID,
+    ) -> Option<HeldInputs> {
+        let device = self.xi1_device(display, device_id)?;
+        // The request is replied to, so catching its errors waits no longer
+        let (state, error_code) =
+            xerror::trap_errors(display, || (self.query_device_state)(display, device));
+        if error_code.is_some() {
+            // The device was removed before the event telling so was processed
+            if !state.is_null() {
+                (self.free_device_state)(state);
+            }
+            self.forget_device(display, device_id);
+            return None;
+        }
+        if state.is_null() {
+            return None;
+        }
+
+        let mut held = HeldInputs {
+            keys: [0; 32],
+            buttons: Vec::new(),
+            in_proximity: true,
+        };
+        // The classes follow each other, each starting with its type and length
+        let mut class = (*state).data as *const u8;
+        for _ in 0..(*state).num_classes {
+            let info = &*(class as *const xi1::XInputClass);
+            match info.class {
+                KEY_CLASS => held.keys = (*(class as *const XKeyState)).keys,
+                BUTTON_CLASS => {
+                    let buttons = &*(class as *const XButtonState);
+                    held.buttons = held_buttons(&buttons.buttons, buttons.num_buttons);
+                }
+                VALUATOR_CLASS => {
+                    let valuators = &*(class as *const XValuatorState);
+                    held.in_proximity = valuators.mode & OUT_OF_PROXIMITY == 0;
+                }
+                _ => {}
+            }
+
+            if info.length == 0 {
+                break;
+            }
+            class = class.add(info.length as usize);
+        }
+
+        (self.free_device_state)(state);
+        Some(held)
+    }
+
+    /// Get the ids of the devices that report relative motion, such as mice and
+    /// touchpads, rather than absolute positions like tablets and touchscreens
+    pub(super) unsafe fn relative_devices(&self, display: *mut xlib::Display) -> HashSet<c_int> {
+        let mut relative = HashSet::new();
+        let mut count = 0;
+        let devices = (self.query_device)(display, xi::XIAllDevices, &mut count);
+        if devices.is_null() {
+            return relative;
+        }
+
+        for device in slice::from_raw_parts(devices, count as usize) {
+            let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+            let relative_x = classes.iter().any(|class| {
+                (**class)._type == xi::XIValuatorClass && {
+                    let class = &*(*class as *const xi::XIValuatorClassInfo);
+                    class.number == 0 && class.mode == xi::XIModeRelative
+                }
+            });
+
+            if relative_x {
+                relative.insert(device.deviceid);
+            }
+        }
+
+        (self.free_device_info)(devices);
+        relative
+    }
+
+    /// Query the pointer of this client for its position, the window under it and the
+    /// held buttons and modifiers, all in one request
+    pub(super) unsafe fn query_pointer(
+        &self,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+    ) -> Option<Pointer> {
+        let device = self.client_pointer(display)?;
+        let (mut root_return, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0.0, 0.0, 0.0, 0.0);
+        let mut buttons = xi::XIButtonState {
+            mask_len: 0,
+            mask: ptr::null_mut(),
+        };
+        let (mut modifiers, mut group) = (Default::default(), Default::default());
+
+        let same_screen = (self.query_pointer)(
+            display,
+            device,
+            root,
+            &mut root_return,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut win_x,
+            &mut win_y,
+            &mut buttons,
+            &mut modifiers,
+            &mut group,
+        ) != 0;
+
+        let held = if buttons.mask.is_null() {
+            Vec::new()
+        } else {
+            let mask = slice::from_raw_parts(buttons.mask, buttons.mask_len.max(0) as usize);
+            // Bit 0 of the mask stands for no button
+            let held = (1..=u8::MAX)
+                .take_while(|button| (*button as usize) < mask.len() * 8)
+                .filter(|button| xi::XIMaskIsSet(mask, *button as i32))
+                .collect();
+            xlib::XFree(buttons.mask as *mut std::ffi::c_void);
+            held
+        };
+
+        Some(Pointer {
+            // XI2 coordinates have a fractional part the core protocol drops
+            coordinates: (root_x.floor() as i32, root_y.floor() as i32),
+            buttons: held,
+            modifiers: modifiers.effective as c_uint,
+            same_screen,
+            child,
+        })
+    }
+
+    /// Get the master pointer of this client, asking the server only after a
+    /// change of the device hierarchy
+    pub(super) unsafe fn client_pointer(&self, display: *mut xlib::Display) -> Option<c_int> {
+        if let Some(device) = self.client_pointer.get() {
+            return Some(device);
+        }
+
+        let mut device = 0;
+        if (self.get_client_pointer)(display, 0, &mut device) == 0 {
+            return None;
+        }
+        self.client_pointer.set(Some(device));
+        Some(device)
+    }
+
+    /// Forget the master pointer of this client, which may have been removed
+    pub(super) fn forget_client_pointer(&self) {
+        self.client_pointer.set(None);
+    }
+
+    /// Get the XInput 1 handle of a physical device, opening it the first time
+    unsafe fn xi1_device(
+        &self,
+        display: *mut xlib::Display,
+        device_id: xlib::XID,
+    ) -> Option<*mut xi1::XDevice> {
+        if let Some(device) = self.open_devices.borrow().get(&device_id) {
+            return Some(*device);
+        }
+
+        // Master devices cannot be opened
+        let (device, error_code) =
+            xerror::trap_errors(display, || (self.open_device)(display, device_id));
+        if device.is_null() || error_code.is_some() {
+            return None;
+        }
+        self.open_devices.borrow_mut().insert(device_id, device);
+        Some(device)
+    }
+
+    /// Close the XInput 1 handle of a device that was removed, if it was opened
+    pub(super) unsafe fn forget_device(&self, display: *mut xlib::Display, device_id: xlib::XID) {
+        if let Some(device) = self.open_devices.borrow_mut().remove(&device_id) {
+            // The server no longer knows the device
+            xerror::trap_errors(display, || (self.close_device)(display, device));
+        }
+    }
+
+    /// Close the XInput 1 handles of all the opened devices
+    pub(super) unsafe fn close_devices(&self, display: *mut xlib::Display) {
+        let devices: Vec<_> = self.open_devices.borrow_mut().drain().collect();
+        xerror::trap_errors(display, || {
+            for (_, device) in devices {
+                (self.close_device)(display, device);
+            }
+        });
+    }
+}
+
+/// Get the ids of the devices a hierarchy event reports as added and as removed
+pub(super) unsafe fn hierarchy_changes(event: &xi::XIHierarchyEvent) -> (Vec<c_int>, Vec<c_int>) {
+    let infos = if event.info.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(event.info, event.num_info.max(0) as usize)
+    };
+    let with_flag = |flags| {
+        infos
+            .iter()
+            .filter(|info| info.flags & flags != 0)
+            .map(|info| info.deviceid)
+            .collect()
+    };
+
+    (
+        with_flag(xi::XIMasterAdded | xi::XISlaveAdded),
+        with_flag(xi::XIMasterRemoved | xi::XISlaveRemoved),
+    )
+}
+
+/// Get the numbers of the buttons set in a button bit vector, where bit 0 stands
+/// for no button
+fn held_buttons(mask: &[c_char; 32], num_buttons: c_short) -> Vec<u8> {
+    (1..=num_buttons.clamp(0, 255) as u8)
+        .filter(|button| mask[*button as usize / 8] as u8 & (1 << (button % 8)) != 0)
+        .collect()
+}
+
+/// Tell what a device is from its use, whether it has keys and buttons, whether it
+/// is a touchscreen and whether it measures pressure like tablets do
+fn device_kind(
+    device_use: c_int,
+    has_keys: bool,
+    has_buttons: bool,
+    direct_touch: bool,
+    pressure: bool,
+) -> DeviceKind {
+    match device_use {
+        // Master devices carry the classes of the last device used through them
+        xi::XIMasterPointer => DeviceKind::Pointer,
+        xi::XIMasterKeyboard => DeviceKind::Keyboard,
+        _ if direct_touch => DeviceKind::Touch,
+        _ if pressure => DeviceKind::Tablet,
+        xi::XISlavePointer => DeviceKind::Pointer,
+        xi::XISlaveKeyboard => DeviceKind::Keyboard,
+        _ if has_buttons => DeviceKind::Pointer,
+        _ if has_keys => DeviceKind::Keyboard,
+        _ => DeviceKind::Other,
+    }
+}
+
+/// Get the axes of a device with their labels and current values
+unsafe fn valuator_ranges(
+    classes: &[*mut xi::XIAnyClassInfo],
+) -> Vec<(xlib::Atom, ValuatorRange, f64)> {
+    classes
+        .iter()
+        .filter(|class| (***class)._type == xi::XIValuatorClass)
+        .map(|class| {
+            let class = &*(*class as *const xi::XIValuatorClassInfo);
+            let range = ValuatorRange {
+                number: class.number,
+                minimum: class.min,
+                maximum: class.max,
+            };
+            (class.label, range, class.value)
+        })
+        .collect()
+}
+
+fn labelled_range(
+    ranges: &[(xlib::Atom, ValuatorRange, f64)],
+    label: xlib::Atom,
+) -> Option<ValuatorRange> {
+    ranges
+        .iter()
+        .find(|(atom, _, _)| *atom != 0 && *atom == label)
+        .map(|(_, range, _)| *range)
+}
+
+fn numbered_range(
+    ranges: &[(xlib::Atom, ValuatorRange, f64)],
+    number: c_int,
+) -> Option<ValuatorRange> {
+    ranges
+        .iter()
+        .find(|(_, range, _)| range.number == number)
+        .map(|(_, range, _)| *range)
+}
+
+/// Build the state of a pen from the values of its valuators and the state of its
+/// device. Button 1 is the tip, 2 and 3 the barrel buttons and 8 onwards the buttons
+/// after them, 4 to 7 being kept for scrolling.
+fn pen_state(
+    device: u64,
+    name: &str,
+    values: &[(c_int, f64)],
+    valuators: &PenValuators,
+    screen: (u32, u32),
+    held: Option<&HeldInputs>,
+) -> PenState {
+    let value = |range: ValuatorRange| {
+        values
+            .iter()
+            .find(|(number, _)| *number == range.number)
+            .map_or(0.0, |(_, value)| *value)
+    };
+
+    let tool = if name.to_lowercase().contains("eraser") {
+        PenTool::Eraser
+    } else {
+        PenTool::Pen
+    };
+    // Unlike the raw values of events, the values the server keeps for a device went
+    // through its coordinate transformation matrix, so they span the whole screen
+    let position = (
+        valuators.x.fraction(value(valuators.x)) * f64::from(screen.0),
+        valuators.y.fraction(value(valuators.y)) * f64::from(screen.1),
+    );
+    let pressure = valuators
+        .pressure
+        .map_or(0.0, |range| range.fraction(value(range)));
+    let tilt = |range: Option<ValuatorRange>| {
+        range.map_or(0.0, |range| range.signed_fraction(value(range)))
+    };
+
+    let buttons = held.map_or(&[][..], |held| &held.buttons[..]);
+    let barrel_buttons: Vec<u8> = buttons
+        .iter()
+        .filter_map(|button| match button {
+            2 | 3 => Some(button - 1),
+            8..=u8::MAX => Some(button - 5),
+            _ => None,
+        })
+        .collect();
+    let in_proximity = match held {
+        Some(held) => held.in_proximity,
+        None => true,
+    };
+
+    PenState::from(device, name, tool, position)
+        .with_pressure(pressure)
+        .with_tilt((tilt(valuators.tilt_x), tilt(valuators.tilt_y)))
+        .with_barrel_buttons(&barrel_buttons)
+        .with_contact(buttons.contains(&1), in_proximity)
+}
+
+fn event_mask(deviceid: c_int, mask: &mut [c_uchar]) -> xi::XIEventMask {
+    xi::XIEventMask {
+        deviceid,
+        mask_len: mask.len() as c_int,
+        mask: mask.as_mut_ptr(),
+    }
+}
+
+/// Get the scrolling of a raw XI2 event, `None` for other events. Wheel notches
+/// the server emulates from smooth scrolling are skipped, as the smooth scrolling
+/// itself is reported.
+pub(super) unsafe fn raw_scroll(
+    event: &xi::XIRawEvent,
+    scroll_valuators: &HashMap<c_int, Vec<ScrollValuator>>,
+) -> Option<ScrollDelta> {
+    match event.evtype {
+        xi::XI_RawButtonPress if event.flags & xi::XIPointerEmulated == 0 => {
+            button_scroll(event.detail)
+        }
+        xi::XI_RawMotion => {
+            let scroll_valuators = scroll_valuators.get(&event.sourceid)?;
+            let mask =
+                slice::from_raw_parts(event.valuators.mask, event.valuators.mask_len as usize);
+            let set_count = mask.iter().map(|byte| byte.count_ones() as usize).sum();
+            let values = slice::from_raw_parts(event.valuators.values, set_count);
+
+            Some(valuator_scroll(
+                &valuator_values(mask, values),
+                scroll_valuators,
+            ))
+            .filter(|delta| !delta.is_zero())
+        }
+        _ => None,
+    }
+}
+
+/// Get the unaccelerated movement of a raw XI2 motion event, `None` for other events
+/// and for devices reporting absolute positions
+pub(super) unsafe fn raw_motion(
+    event: &xi::XIRawEvent,
+    relative_devices: &HashSet<c_int>,
+) -> Option<MotionDelta> {
+    if event.evtype != xi::XI_RawMotion || !relative_devices.contains(&event.sourceid) {
+        return None;
+    }
+
+    let mask = slice::from_raw_parts(event.valuators.mask, event.valuators.mask_len as usize);
+    let set_count = mask.iter().map(|byte| byte.count_ones() as usize).sum();
+    let raw_values = slice::from_raw_parts(event.raw_values, set_count);
+
+    Some(valuator_motion(&valuator_values(mask, raw_values))).filter(|delta| !delta.is_zero())
+}
+
+/// Get where a raw XI2 touch event puts a touch on a screen of this size, the values
+/// it does not carry being those of the touch before the event, if any
+pub(super) unsafe fn raw_touch(
+    event: &xi::XIRawEvent,
+    valuators: &TouchValuators,
+    screen: (u32, u32),
+    previous: Option<&RawTouch>,
+) -> RawTouch {
+    let mask = slice::from_raw_parts(event.valuators.mask, event.valuators.mask_len as usize);
+    let set_count = mask.iter().map(|byte| byte.count_ones() as usize).sum();
+    // The raw values are the ones the device reported, the matrix is applied here
+    let values = slice::from_raw_parts(event.raw_values, set_count);
+
+    touch_point(
+        event.detail as u32 as u64,
+        event.sourceid as u64,
+        &valuator_values(mask, values),
+        valuators,
+        screen,
+        previous,
+    )
+}
+
+/// Fill a touch from the values of its valuators
+fn touch_point(
+    id: u64,
+    device: u64,
+    values: &[(c_int, f64)],
+    valuators: &TouchValuators,
+    screen: (u32, u32),
+    previous: Option<&RawTouch>,
+) -> RawTouch {
+    let value = |range: Option<ValuatorRange>| {
+        let range = range?;
+        values
+            .iter()
+            .find(|(number, _)| *number == range.number)
+            .map(|(_, value)| *value)
+    };
+
+    let previous_fractions = previous.map_or((0.0, 0.0), |touch| touch.fractions);
+    let fractions = (
+        value(Some(valuators.x)).map_or(previous_fractions.0, |x| valuators.x.fraction(x)),
+        value(Some(valuators.y)).map_or(previous_fractions.1, |y| valuators.y.fraction(y)),
+    );
+    let (x, y) = transform(&valuators.matrix, fractions);
+    let position = (x * f64::from(screen.0), y * f64::from(screen.1));
+    let mut touch = TouchPoint::from(id, position).with_device(device);
+    let previous = previous.map(|touch| &touch.point);
+
+    let pressure = match valuators.pressure {
+        Some(range) => value(Some(range)).map(|pressure| range.fraction(pressure)),
+        None => None,
+    };
+    if let Some(pressure) = pressure.or_else(|| previous.and_then(|touch| touch.pressure())) {
+        touch = touch.with_pressure(pressure);
+    }
+
+    // The contact area is measured in the units of the position axes, and shrinks
+    // with the X axis when the matrix maps the touchscreen to a part of the screen
+    let matrix = &valuators.matrix;
+    let scale = (matrix[0] * matrix[0] + matrix[3] * matrix[3]).sqrt();
+    let contact = |length: f64| valuators.x.screen_distance(length, screen.0) * scale;
+    let major = value(valuators.touch_major).map(contact);
+    let minor = value(valuators.touch_minor).map(contact);
+    let size = match (major, minor) {
+        (Some(major), minor) => Some((major, minor.unwrap_or(major))),
+        (None, _) => previous.and_then(|touch| touch.size()),
+    };
+    if let Some(size) = size {
+        touch = touch.with_size(size);
+    }
+
+    RawTouch {
+        point: touch,
+        fractions,
+    }
+}
+
+/// Apply a coordinate transformation matrix to a point
+fn transform(matrix: &[f64; 9], (x, y): (f64, f64)) -> (f64, f64) {
+    let w = matrix[6] * x + matrix[7] * y + matrix[8];
+    if w == 0.0 {
+        return (x, y);
+    }
+
+    (
+        (matrix[0] * x + matrix[1] * y + matrix[2]) / w,
+        (matrix[3] * x + matrix[4] * y + matrix[5]) / w,
+    )
+}
+
+/// Get the movement along the first two valuators, which are the X and Y axes of
+/// pointing devices
+fn valuator_motion(values: &[(c_int, f64)]) -> MotionDelta {
+    let value = |axis| {
+        values
+            .iter()
+            .find(|(number, _)| *number == axis)
+            .map_or(0.0, |(_, value)| *value)
+    };
+
+    MotionDelta::from(value(0), value(1))
+}
+
+/// Get the notch a wheel button stands for: 4 and 5 scroll up and down,
+/// 6 and 7 scroll left and right
+fn button_scroll(button: c_int) -> Option<ScrollDelta> {
+    match button {
+        4 => Some(ScrollDelta::from(0.0, -1.0)),
+        5 => Some(ScrollDelta::from(0.0, 1.0)),
+        6 => Some(ScrollDelta::from(-1.0, 0.0)),
+        7 => Some(ScrollDelta::from(1.0, 0.0)),
+        _ => None,
+    }
+}
+
+/// Pair the packed values of an event with the numbers of the valuators set in its mask
+fn valuator_values(mask: &[c_uchar], values: &[f64]) -> Vec<(c_int, f64)> {
+    (0..mask.len() as c_int * 8)
+        .filter(|number| xi::XIMaskIsSet(mask, *number))
+        .zip(values.iter().copied())
+        .collect()
+}
+
+/// Convert the motion along scrolling axes to notches
+fn valuator_scroll(values: &[(c_int, f64)], scroll_valuators: &[ScrollValuator]) -> ScrollDelta {
+    let mut delta = ScrollDelta::default();
+
+    for (number, value) in values {
+        for valuator in scroll_valuators {
+            if valuator.number != *number || valuator.increment == 0.0 {
+                continue;
+            }
+
+            let notches = value / valuator.increment;
+            delta += if valuator.horizontal {
+                ScrollDelta::from(notches, 0.0)
+            } else {
+                ScrollDelta::from(0.0, notches)
+            };
+        }
+    }
+
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_buttons_test() {
+        let mut mask = [0; 32];
+        mask[0] = 0b1000_1010u8 as c_char;
+        mask[1] = 0b0000_0001;
+
+        assert_eq!(held_buttons(&mask, 9), vec![1, 3, 7, 8]);
+        assert_eq!(held_buttons(&mask, 5), vec![1, 3]);
+        assert!(held_buttons(&mask, 0).is_empty());
+    }
+
+    #[test]
+    fn hierarchy_changes_test() {
+        let info = |deviceid, flags| xi::XIHierarchyInfo {
+            deviceid,
+            attachment: 0,
+            _use: xi::XISlavePointer,
+            enabled: xlib::True,
+            flags,
+        };
+        let mut infos = [
+            info(9, xi::XISlaveAdded | xi::XIDeviceEnabled),
+            info(10, xi::XISlaveRemoved),
+            info(11, xi::XISlaveAttached),
+            info(12, xi::XIMasterAdded),
+        ];
+
+        let mut event: xi::XIHierarchyEvent = unsafe { std::mem::zeroed() };
+        event.num_info = infos.len() as c_int;
+        event.info = infos.as_mut_ptr();
+        assert_eq!(
+            unsafe { hierarchy_changes(&event) },
+            (vec![9, 12], vec![10])
+        );
+
+        event.info = ptr::null_mut();
+        assert_eq!(unsafe { hierarchy_changes(&event) }, (vec![], vec![]));
+    }
+
+    #[test]
+    fn device_kind_test() {
+        assert_eq!(
+            device_kind(xi::XIMasterPointer, false, true, true, false),
+            DeviceKind::Pointer
+        );
+        assert_eq!(
+            device_kind(xi::XISlavePointer, false, true, true, false),
+            DeviceKind::Touch
+        );
+        assert_eq!(
+            device_kind(xi::XISlavePointer, false, true, false, true),
+            DeviceKind::Tablet
+        );
+        assert_eq!(
+            device_kind(xi::XISlaveKeyboard, true, false, false, false),
+            DeviceKind::Keyboard
+        );
+        assert_eq!(
+            device_kind(xi::XIFloatingSlave, true, false, false, false),
+            DeviceKind::Keyboard
+        );
+        assert_eq!(
+            device_kind(xi::XIFloatingSlave, false, false, false, false),
+            DeviceKind::Other
+        );
+    }
+
+    #[test]
+    fn button_scroll_test() {
+        assert_eq!(button_scroll(4), Some(ScrollDelta::from(0.0, -1.0)));
+        assert_eq!(button_scroll(7), Some(ScrollDelta::from(1.0, 0.0)));
+        assert_eq!(button_scroll(3), None);
+        assert_eq!(button_scroll(8), None);
+    }
+
+    #[test]
+    fn valuator_motion_test() {
+        let values = valuator_values(&[0b1101], &[4.0, 30.0, 5.0]);
+        assert_eq!(valuator_motion(&values), MotionDelta::from(4.0, 0.0));
+
+        let values = valuator_values(&[0b11], &[-1.5, 2.0]);
+        assert_eq!(valuator_motion(&values), MotionDelta::from(-1.5, 2.0));
+
+        assert!(valuator_motion(&valuator_values(&[0b100], &[7.0])).is_zero());
+    }
+
+    #[test]
+    fn touch_point_test() {
+        let range = |number, maximum| ValuatorRange {
+            number,
+            minimum: 0.0,
+            maximum,
+        };
+        let mut valuators = TouchValuators {
+            x: range(0, 4000.0),
+            y: range(1, 2000.0),
+            pressure: Some(range(2, 255.0)),
+            touch_major: Some(range(3, 255.0)),
+            touch_minor: None,
+            matrix: IDENTITY_MATRIX,
+        };
+        let values = valuator_values(&[0b1111], &[1000.0, 500.0, 51.0, 40.0]);
+        let began = touch_point(7, 12, &values, &valuators, (1920, 1080), None);
+        assert_eq!(
+            began.point,
+            TouchPoint::from(7, (480.0, 270.0))
+                .with_device(12)
+                .with_pressure(0.2)
+                .with_size((19.2, 19.2))
+        );
+
+        // Updates only carry the values that changed
+        let values = valuator_values(&[0b10], &[1080.0]);
+        let updated = touch_point(7, 12, &values, &valuators, (1920, 1080), Some(&began));
+        assert_eq!(updated.point.position(), (480.0, 583.2));
+        assert_eq!(updated.point.pressure(), Some(0.2));
+        assert_eq!(updated.point.size(), Some((19.2, 19.2)));
+
+        // A touchscreen mapped to the right half of the screen
+        valuators.matrix = [0.5, 0.0, 0.5, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let values = valuator_values(&[0b1011], &[1000.0, 500.0, 40.0]);
+        let mapped = touch_point(7, 12, &values, &valuators, (1920, 1080), None);
+        assert_eq!(mapped.point.position(), (1200.0, 270.0));
+        assert_eq!(mapped.point.size(), Some((9.6, 9.6)));
+    }
+
+    #[test]
+    fn pen_state_test() {
+        let range = |number, minimum, maximum| ValuatorRange {
+            number,
+            minimum,
+            maximum,
+        };
+        let valuators = PenValuators {
+            x: range(0, 0.0, 40000.0),
+            y: range(1, 0.0, 25000.0),
+            pressure: Some(range(2, 0.0, 2048.0)),
+            tilt_x: Some(range(3, -64.0, 63.0)),
+            tilt_y: Some(range(4, -64.0, 63.0)),
+        };
+        let values = [(0, 10000.0), (1, 12500.0), (2, 512.0), (3, -32.0), (4, 0.0)];
+        let held = HeldInputs {
+            keys: [0; 32],
+            buttons: vec![1, 3, 9],
+            in_proximity: true,
+        };
+
+        let pen = pen_state(
+            9,
+            "Tablet Pen stylus",
+            &values,
+            &valuators,
+            (1920, 1200),
+            Some(&held),
+        );
+        assert_eq!(
+            pen,
+            PenState::from(9, "Tablet Pen stylus", PenTool::Pen, (480.0, 600.0))
+                .with_pressure(0.25)
+                .with_tilt((-0.5, 0.0))
+                .with_barrel_buttons(&[2, 4])
+                .with_contact(true, true)
+        );
+
+        let held = HeldInputs {
+            keys: [0; 32],
+            buttons: Vec::new(),
+            in_proximity: false,
+        };
+        let eraser = pen_state(
+            10,
+            "Tablet Pen eraser",
+            &values,
+            &valuators,
+            (1920, 1200),
+            Some(&held),
+        );
+        assert_eq!(eraser.tool(), PenTool::Eraser);
+        assert!(!eraser.is_touching());
+        assert!(!eraser.in_proximity());
+    }
+
+    #[test]
+    fn valuator_scroll_test() {
+        // Valuators 0 and 1 move the pointer, 2 and 3 scroll
+        let scroll_valuators = [
+            ScrollValuator {
+                number: 2,
+                horizontal: false,
+                increment: 15.0,
+            },
+            ScrollValuator {
+                number: 3,
+                horizontal: true,
+                increment: -10.0,
+            },
+        ];
+
+        let values = valuator_values(&[0b1101], &[4.0, 30.0, 5.0]);
+        assert_eq!(values, vec![(0, 4.0), (2, 30.0), (3, 5.0)]);
+        assert_eq!(
+            valuator_scroll(&values, &scroll_valuators),
+            ScrollDelta::from(-0.5, 2.0)
+        );
+
+        let values = valuator_values(&[0b11], &[4.0, -2.0]);
+        assert!(valuator_scroll(&values, &scroll_valuators).is_zero());
+    }
+}
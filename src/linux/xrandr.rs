@@ -0,0 +1,117 @@
+//! Monitor geometry from the X Resize and Rotate extension, whose library is
+//! loaded at runtime
+
+use super::{library::Library, property};
+use crate::Monitor;
+use std::{os::raw::c_int, slice};
+use x11::{xlib, xrandr};
+
+type QueryExtension =
+    unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> xlib::Bool;
+type QueryVersion =
+    unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> xlib::Status;
+type SelectInput = unsafe extern "C" fn(*mut xlib::Display, xlib::Window, c_int);
+type GetMonitors = unsafe extern "C" fn(
+    *mut xlib::Display,
+    xlib::Window,
+    xlib::Bool,
+    *mut c_int,
+) -> *mut xrandr::XRRMonitorInfo;
+type FreeMonitors = unsafe extern "C" fn(*mut xrandr::XRRMonitorInfo);
+type UpdateConfiguration = unsafe extern "C" fn(*mut xlib::XEvent) -> c_int;
+
+/// The XRandR functions, available when both the library and the server support
+/// RandR 1.5, which introduced monitors
+pub(super) struct XRandr {
+    event_base: c_int,
+    get_monitors: GetMonitors,
+    free_monitors: FreeMonitors,
+    update_configuration: UpdateConfiguration,
+    _library: Library,
+}
+
+impl XRandr {
+    /// Load libXrandr and ask the server to report monitor changes on the root window,
+    /// `None` if either lacks RandR 1.5
+    pub(super) unsafe fn load(display: *mut xlib::Display, root: xlib::Window) -> Option<XRandr> {
+        let library = Library::open(&["libXrandr.so.2", "libXrandr.so"])?;
+        let query_extension: QueryExtension = library.function("XRRQueryExtension")?;
+        let query_version: QueryVersion = library.function("XRRQueryVersion")?;
+        let select_input: SelectInput = library.function("XRRSelectInput")?;
+
+        let (mut event_base, mut error_base) = (0, 0);
+        if query_extension(display, &mut event_base, &mut error_base) == 0 {
+            return None;
+        }
+
+        let (mut major, mut minor) = (0, 0);
+        if query_version(display, &mut major, &mut minor) == 0 || (major, minor) < (1, 5) {
+            return None;
+        }
+
+        select_input(
+            display,
+            root,
+            xrandr::RRScreenChangeNotifyMask
+                | xrandr::RRCrtcChangeNotifyMask
+                | xrandr::RROutputChangeNotifyMask,
+        );
+
+        Some(XRandr {
+            event_base,
+            get_monitors: library.function("XRRGetMonitors")?,
+            free_monitors: library.function("XRRFreeMonitors")?,
+            update_configuration: library.function("XRRUpdateConfiguration")?,
+            _library: library,
+        })
+    }
+
+    /// Check whether an event reports a change of the monitors, updating the screen
+    /// size Xlib knows about if it does
+    pub(super) unsafe fn process_event(&self, event: &mut xlib::XEvent) -> bool {
+        let kind = event.get_type() - self.event_base;
+        if kind != xrandr::RRScreenChangeNotify && kind != xrandr::RRNotify {
+            return false;
+        }
+
+        (self.update_configuration)(event);
+        true
+    }
+
+    /// Get the active monitors of a screen
+    pub(super) unsafe fn monitors(
+        &self,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+        scale: f64,
+    ) -> Vec<Monitor> {
+        let mut count = 0;
+        let infos = (self.get_monitors)(display, root, xlib::True, &mut count);
+        if infos.is_null() {
+            return Vec::new();
+        }
+
+        let monitors = slice::from_raw_parts(infos, count.max(0) as usize)
+            .iter()
+            .map(|info| {
+                let monitor = Monitor::from(
+                    &property::atom_name(display, info.name),
+                    (info.x, info.y),
+                    (info.width.max(0) as u32, info.height.max(0) as u32),
+                    info.primary != 0,
+                    scale,
+                );
+
+                // Outputs without EDID, such as projectors, report a size of zero
+                if info.mwidth > 0 && info.mheight > 0 {
+                    monitor.with_physical_size((info.mwidth as u32, info.mheight as u32))
+                } else {
+                    monitor
+                }
+            })
+            .collect();
+
+        (self.free_monitors)(infos);
+        monitors
+    }
+}
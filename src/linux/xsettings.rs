@@ -0,0 +1,119 @@
+//! Reading the desktop settings shared through the XSETTINGS protocol
+
+use super::property;
+use std::convert::TryInto;
+use x11::xlib;
+
+/// Fetch the settings of the default screen's settings manager, to be read with
+/// `parse_int()`, `None` if no manager runs
+pub(super) unsafe fn fetch(display: *mut xlib::Display) -> Option<Vec<u8>> {
+    let selection = format!("_XSETTINGS_S{}", xlib::XDefaultScreen(display));
+    let owner = xlib::XGetSelectionOwner(display, property::intern_atom(display, &selection));
+    if owner == 0 {
+        return None;
+    }
+
+    let settings = property::get_property(
+        display,
+        owner,
+        property::intern_atom(display, "_XSETTINGS_SETTINGS"),
+    )?;
+
+    settings.bytes().map(<[u8]>::to_vec)
+}
+
+/// Find an integer setting, such as `Net/DoubleClickTime`, in the value of the
+/// `_XSETTINGS_SETTINGS` property
+pub(super) fn parse_int(data: &[u8], name: &str) -> Option<i32> {
+    let big_endian = *data.first()? == 1;
+    let card16 = |at: usize| -> Option<usize> {
+        let bytes = data.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        } as usize)
+    };
+    let card32 = |at: usize| -> Option<u32> {
+        let bytes = data.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let padded = |len: usize| (len + 3) & !3;
+
+    // Byte order and padding, serial, then the number of settings
+    let count = card32(8)?;
+    let mut at = 12;
+
+    for _ in 0..count {
+        let kind = *data.get(at)?;
+        let name_len = card16(at + 2)?;
+        let setting_name = data.get(at + 4..at + 4 + name_len)?;
+
+        // Skip the name and the serial of the last change
+        at += 4 + padded(name_len) + 4;
+
+        let value_len = match kind {
+            0 => 4,
+            1 => 4 + padded(card32(at)? as usize),
+            2 => 8,
+            _ => return None,
+        };
+
+        if kind == 0 && setting_name == name.as_bytes() {
+            return Some(card32(at)? as i32);
+        }
+        at += value_len;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode settings the way a little endian settings manager does
+    fn encode(settings: &[(&str, u8, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 0];
+        data.extend(&7_u32.to_le_bytes());
+        data.extend(&(settings.len() as u32).to_le_bytes());
+
+        for (name, kind, value) in settings {
+            data.extend(&[*kind, 0]);
+            data.extend(&(name.len() as u16).to_le_bytes());
+            data.extend(name.as_bytes());
+            data.resize((data.len() + 3) & !3, 0);
+            data.extend(&0_u32.to_le_bytes());
+            data.extend(*value);
+        }
+
+        data
+    }
+
+    #[test]
+    fn parse_int_test() {
+        let mut theme = 3_u32.to_le_bytes().to_vec();
+        theme.extend(b"Foo\0");
+
+        let data = encode(&[
+            ("Net/ThemeName", 1, &theme),
+            ("Gtk/CursorThemeColor", 2, &[0; 8]),
+            ("Net/DoubleClickTime", 0, &250_i32.to_le_bytes()),
+            ("Net/DoubleClickDistance", 0, &8_i32.to_le_bytes()),
+        ]);
+
+        assert_eq!(parse_int(&data, "Net/DoubleClickTime"), Some(250));
+        assert_eq!(parse_int(&data, "Net/DoubleClickDistance"), Some(8));
+        assert_eq!(parse_int(&data, "Net/ThemeName"), None);
+        assert_eq!(parse_int(&data, "Net/CursorBlink"), None);
+        assert_eq!(
+            parse_int(&data[..data.len() - 2], "Net/DoubleClickDistance"),
+            None
+        );
+        assert_eq!(parse_int(&[], "Net/DoubleClickTime"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::KeyCode;
+
+/// The state of the keyboard modifiers at the moment of a query.
+///
+/// `Shift`, `Control`, `Alt` and `Meta` are active while the key is held,
+/// `CapsLock` and `NumLock` are active while the lock is engaged.
+/// ```rust
+/// # fn example_modifiers() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceQuery, DeviceState, Modifier};
+/// # let device_state = DeviceState::new()?;
+/// let state = device_state.get_state(); // get_state() from the `DeviceQuery` trait
+/// if state.modifiers().is_active(Modifier::Control) && state.mouse().get_buttons().len() > 0 {
+///     println!("Ctrl+click");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Modifiers {
+    bits: u8,
+}
+
+impl Modifiers {
+    /// Create a Modifiers with the given modifiers active
+    pub fn new(modifiers: &[Modifier]) -> Self {
+        Self {
+            bits: modifiers.iter().fold(0, |bits, m| bits | m.bit()),
+        }
+    }
+
+    /// Check whether a modifier is active
+    pub fn is_active(&self, modifier: Modifier) -> bool {
+        self.bits & modifier.bit() != 0
+    }
+
+    /// Check whether no modifier is active
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Create a Modifiers with the modifiers the given keys set while held.
+    /// Caps Lock and Num Lock are never active, as the keys do not tell whether a lock is engaged.
+    pub fn from_keys(keys: &[KeyCode]) -> Self {
+        let held: Vec<Modifier> = keys.iter().filter_map(Modifier::from_key).collect();
+        Self::new(&held)
+    }
+
+    /// Get a vector of the currently active Modifiers
+    pub fn get_modifiers(&self) -> Vec<Modifier> {
+        Modifier::ALL
+            .iter()
+            .filter(|m| self.is_active(**m))
+            .copied()
+            .collect()
+    }
+}
+
+/// The keyboard modifiers reported by Modifiers
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    /// Also known as Super or the Windows key
+    Meta,
+    CapsLock,
+    NumLock,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 6] = [
+        Modifier::Shift,
+        Modifier::Control,
+        Modifier::Alt,
+        Modifier::Meta,
+        Modifier::CapsLock,
+        Modifier::NumLock,
+    ];
+
+    /// Get the modifier a key sets while it is held
+    pub(crate) fn from_key(key: &KeyCode) -> Option<Modifier> {
+        match key {
+            KeyCode::LControl | KeyCode::RControl => Some(Modifier::Control),
+            KeyCode::LShift | KeyCode::RShift => Some(Modifier::Shift),
+            KeyCode::LAlt | KeyCode::RAlt => Some(Modifier::Alt),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_test() {
+        let modifiers = Modifiers::default();
+
+        assert!(modifiers.is_empty());
+        assert_eq!(modifiers.get_modifiers(), Vec::default());
+        assert_eq!(modifiers, Modifiers::new(&[]));
+    }
+
+    #[test]
+    fn active_test() {
+        let modifiers = Modifiers::new(&[Modifier::NumLock, Modifier::Control]);
+
+        assert!(!modifiers.is_empty());
+        assert!(modifiers.is_active(Modifier::Control));
+        assert!(modifiers.is_active(Modifier::NumLock));
+        assert!(!modifiers.is_active(Modifier::Shift));
+        assert_eq!(
+            modifiers.get_modifiers(),
+            vec![Modifier::Control, Modifier::NumLock]
+        );
+    }
+
+    #[test]
+    fn from_keys_test() {
+        let modifiers = Modifiers::from_keys(&[KeyCode::RControl, KeyCode::A, KeyCode::LShift]);
+
+        assert_eq!(
+            modifiers,
+            Modifiers::new(&[Modifier::Control, Modifier::Shift])
+        );
+        assert!(Modifiers::from_keys(&[KeyCode::A]).is_empty());
+    }
+}
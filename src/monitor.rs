@@ -0,0 +1,126 @@
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+/// A monitor showing part of the desktop, as returned by `DeviceState::query_monitors()`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Monitor {
+    name: String,
+    position: (i32, i32),
+    size: (u32, u32),
+    primary: bool,
+    scale: f64,
+    physical_size: Option<(u32, u32)>,
+}
+
+impl Monitor {
+    /// Get the name of the monitor, such as `DP-1` on Linux or `\\.\DISPLAY1` on Windows
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the desktop coordinates of the top left corner of the monitor
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    /// Get the width and height of the monitor in pixels
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Check whether the monitor is the primary one
+    pub fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Get how much the desktop scales up its interface on the monitor, 1.0 for 96 DPI
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Get the width and height of the monitor in millimeters, `None` if the monitor
+    /// does not report them
+    pub fn physical_size(&self) -> Option<(u32, u32)> {
+        self.physical_size
+    }
+
+    /// Get the horizontal and vertical pixel density of the monitor in dots per inch,
+    /// `None` if its physical size is unknown
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        let (width, height) = self.physical_size.filter(|(w, h)| *w > 0 && *h > 0)?;
+
+        Some((
+            f64::from(self.size.0) / f64::from(width) * MILLIMETERS_PER_INCH,
+            f64::from(self.size.1) / f64::from(height) * MILLIMETERS_PER_INCH,
+        ))
+    }
+
+    /// Check whether the monitor shows a point of the desktop
+    pub fn contains(&self, coordinates: (i32, i32)) -> bool {
+        let (x, y) = (
+            i64::from(coordinates.0) - i64::from(self.position.0),
+            i64::from(coordinates.1) - i64::from(self.position.1),
+        );
+
+        x >= 0 && y >= 0 && x < i64::from(self.size.0) && y < i64::from(self.size.1)
+    }
+
+    /// Convert desktop coordinates to coordinates relative to the top left corner of the monitor
+    pub fn to_local(&self, coordinates: (i32, i32)) -> (i32, i32) {
+        (
+            coordinates.0 - self.position.0,
+            coordinates.1 - self.position.1,
+        )
+    }
+
+    /// Create a Monitor from its name, position, size, whether it is the primary one and its scale
+    pub fn from(
+        name: &str,
+        position: (i32, i32),
+        size: (u32, u32),
+        primary: bool,
+        scale: f64,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            position,
+            size,
+            primary,
+            scale,
+            physical_size: None,
+        }
+    }
+
+    /// Set the width and height of the monitor in millimeters
+    pub fn with_physical_size(mut self, physical_size: (u32, u32)) -> Self {
+        self.physical_size = Some(physical_size);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_test() {
+        // A monitor on the left of a 1920 pixels wide primary one
+        let monitor = Monitor::from("HDMI-1", (-1280, 100), (1280, 1024), false, 1.0);
+
+        assert!(monitor.contains((-1280, 100)));
+        assert!(monitor.contains((-1, 1123)));
+        assert!(!monitor.contains((0, 500)));
+        assert!(!monitor.contains((-1000, 99)));
+        assert_eq!(monitor.to_local((-1000, 150)), (280, 50));
+    }
+
+    #[test]
+    fn dpi_test() {
+        let monitor = Monitor::from("DP-1", (0, 0), (3840, 2160), true, 1.5);
+        assert_eq!(monitor.dpi(), None);
+
+        let monitor = monitor.with_physical_size((960, 540));
+        assert_eq!(monitor.physical_size(), Some((960, 540)));
+        assert_eq!(monitor.dpi(), Some((101.6, 101.6)));
+        assert_eq!(monitor.clone().with_physical_size((0, 0)).dpi(), None);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::Timestamp;
+use std::ops::{Add, AddAssign};
+
+/// A relative movement of the pointing device, in device units.
+///
+/// The deltas are not accelerated and keep coming when the cursor is pinned against
+/// a screen edge. Positive `dx` values move right and positive `dy` values move down.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct MotionDelta {
+    dx: f64,
+    dy: f64,
+}
+
+impl MotionDelta {
+    /// Get the horizontal movement, positive to the right
+    pub fn dx(&self) -> f64 {
+        self.dx
+    }
+
+    /// Get the vertical movement, positive downwards
+    pub fn dy(&self) -> f64 {
+        self.dy
+    }
+
+    /// Check whether there was no movement at all
+    pub fn is_zero(&self) -> bool {
+        self.dx == 0.0 && self.dy == 0.0
+    }
+
+    /// Create a MotionDelta from horizontal and vertical movements
+    pub fn from(dx: f64, dy: f64) -> Self {
+        Self { dx, dy }
+    }
+}
+
+impl Add for MotionDelta {
+    type Output = MotionDelta;
+
+    fn add(self, other: MotionDelta) -> MotionDelta {
+        MotionDelta::from(self.dx + other.dx, self.dy + other.dy)
+    }
+}
+
+impl AddAssign for MotionDelta {
+    fn add_assign(&mut self, other: MotionDelta) {
+        *self = *self + other;
+    }
+}
+
+/// A single movement reported by the pointing device, as returned by
+/// `DeviceState::query_motion_events()` on Linux
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MotionEvent {
+    delta: MotionDelta,
+    timestamp: Timestamp,
+    device: Option<u64>,
+}
+
+impl MotionEvent {
+    /// Get how much the device moved
+    pub fn delta(&self) -> MotionDelta {
+        self.delta
+    }
+
+    /// Get the moment the device moved
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Get the id of the physical device that produced the event, as in
+    /// `InputDevice::id()`, if known
+    pub fn device(&self) -> Option<u64> {
+        self.device
+    }
+
+    /// Create a MotionEvent from a delta and the moment it happened
+    pub fn from(delta: MotionDelta, timestamp: Timestamp) -> Self {
+        Self {
+            delta,
+            timestamp,
+            device: None,
+        }
+    }
+
+    /// Set the id of the physical device that produced the event
+    pub fn with_device(mut self, device: u64) -> Self {
+        self.device = Some(device);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_test() {
+        let mut delta = MotionDelta::default();
+        assert!(delta.is_zero());
+
+        delta += MotionDelta::from(3.0, -1.0);
+        delta += MotionDelta::from(-0.5, 4.0);
+        assert_eq!(delta, MotionDelta::from(2.5, 3.0));
+        assert!(!delta.is_zero());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{DeviceQuery, MouseState};
+use std::time::Instant;
+
+/// How many pixels make an inch on a monitor of unknown size
+const BASE_DPI: f64 = 96.0;
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+/// Measures how the cursor moves from successive MouseStates: velocity, speed,
+/// acceleration, direction and the distance traveled.
+///
+/// Speeds are in pixels per second and use the timestamps of the MouseStates, so
+/// polling at an irregular rate is fine. The distance in millimeters uses the pixel
+/// density of the monitor attached to each MouseState, and assumes 96 DPI for
+/// MouseStates without one or monitors that do not report their size.
+/// ```rust
+/// # fn example_motion() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, MotionTracker};
+/// # let device_state = DeviceState::new()?;
+/// let mut motion = MotionTracker::new();
+///
+/// loop {
+///     motion.poll(&device_state);
+///     println!(
+///         "{:.0} px/s, {:.1} mm so far",
+///         motion.smoothed_speed(),
+///         motion.distance_mm()
+///     );
+/// #   break;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MotionTracker {
+    smoothing: f64,
+    last: Option<((i32, i32), Instant)>,
+    velocity: (f64, f64),
+    smoothed_speed: f64,
+    acceleration: f64,
+    direction: Option<f64>,
+    distance: f64,
+    distance_mm: f64,
+}
+
+impl Default for MotionTracker {
+    fn default() -> Self {
+        Self::with_smoothing(0.3)
+    }
+}
+
+impl MotionTracker {
+    /// Create a MotionTracker whose smoothed speed gives a weight of 0.3 to each new sample
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a MotionTracker whose smoothed speed gives the given weight, between 0.0
+    /// and 1.0, to each new sample. Lower weights smooth more but lag behind.
+    pub fn with_smoothing(smoothing: f64) -> Self {
+        Self {
+            smoothing: smoothing.clamp(0.0, 1.0),
+            last: None,
+            velocity: (0.0, 0.0),
+            smoothed_speed: 0.0,
+            acceleration: 0.0,
+            direction: None,
+            distance: 0.0,
+            distance_mm: 0.0,
+        }
+    }
+
+    /// Get the velocity between the last two samples in pixels per second, positive
+    /// to the right and downwards
+    pub fn velocity(&self) -> (f64, f64) {
+        self.velocity
+    }
+
+    /// Get the speed between the last two samples in pixels per second
+    pub fn speed(&self) -> f64 {
+        self.velocity.0.hypot(self.velocity.1)
+    }
+
+    /// Get the speed averaged over the recent samples in pixels per second
+    pub fn smoothed_speed(&self) -> f64 {
+        self.smoothed_speed
+    }
+
+    /// Get how fast the speed changed between the last two samples, in pixels per
+    /// second squared, negative when slowing down
+    pub fn acceleration(&self) -> f64 {
+        self.acceleration
+    }
+
+    /// Get the direction of the last movement as an angle in radians from the right,
+    /// clockwise since the vertical axis points down. `None` before the cursor moves.
+    pub fn direction(&self) -> Option<f64> {
+        self.direction
+    }
+
+    /// Get the distance the cursor traveled in pixels
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// Get the distance the cursor traveled in millimeters
+    pub fn distance_mm(&self) -> f64 {
+        self.distance_mm
+    }
+
+    /// Forget the samples and the distance traveled
+    pub fn reset(&mut self) {
+        *self = Self::with_smoothing(self.smoothing);
+    }
+
+    /// Query the mouse and add it as a sample
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) {
+        self.update(&device_state.get_mouse())
+    }
+
+    /// Add a MouseState as a sample. Samples older than the previous one are ignored.
+    pub fn update(&mut self, mouse: &MouseState) {
+        let coordinates = mouse.coordinates();
+        let instant = mouse.timestamp().instant();
+
+        let (last_coordinates, last_instant) = match self.last {
+            Some(last) if instant >= last.1 => last,
+            Some(_) => return,
+            None => {
+                self.last = Some((coordinates, instant));
+                return;
+            }
+        };
+        self.last = Some((coordinates, instant));
+
+        let delta = (
+            f64::from(coordinates.0 - last_coordinates.0),
+            f64::from(coordinates.1 - last_coordinates.1),
+        );
+        let length = delta.0.hypot(delta.1);
+        if length > 0.0 {
+            let dpi = mouse
+                .monitor()
+                .and_then(|monitor| monitor.dpi())
+                .unwrap_or((BASE_DPI, BASE_DPI));
+            self.distance += length;
+            self.distance_mm += (delta.0 / dpi.0).hypot(delta.1 / dpi.1) * MILLIMETERS_PER_INCH;
+            self.direction = Some(delta.1.atan2(delta.0));
+        }
+
+        // Two samples of the same moment tell nothing about the velocity
+        let elapsed = (instant - last_instant).as_secs_f64();
+        if elapsed == 0.0 {
+            return;
+        }
+
+        let previous_speed = self.speed();
+        self.velocity = (delta.0 / elapsed, delta.1 / elapsed);
+        self.acceleration = (self.speed() - previous_speed) / elapsed;
+        self.smoothed_speed += self.smoothing * (self.speed() - self.smoothed_speed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Monitor, Timestamp};
+    use std::time::Duration;
+
+    fn sample(start: Instant, millis: u64, coordinates: (i32, i32)) -> MouseState {
+        MouseState::from(coordinates, &[])
+            .with_timestamp(Timestamp::from(start + Duration::from_millis(millis), None))
+    }
+
+    #[test]
+    fn motion_test() {
+        let start = Instant::now();
+        let mut motion = MotionTracker::with_smoothing(0.5);
+
+        motion.update(&sample(start, 0, (100, 100)));
+        assert_eq!(motion.speed(), 0.0);
+        assert_eq!(motion.direction(), None);
+
+        motion.update(&sample(start, 100, (130, 140)));
+        assert_eq!(motion.velocity(), (300.0, 400.0));
+        assert_eq!(motion.speed(), 500.0);
+        assert_eq!(motion.smoothed_speed(), 250.0);
+        assert_eq!(motion.acceleration(), 5000.0);
+        assert_eq!(motion.distance(), 50.0);
+
+        motion.update(&sample(start, 200, (130, 110)));
+        assert_eq!(motion.velocity(), (0.0, -300.0));
+        assert_eq!(motion.smoothed_speed(), 275.0);
+        assert_eq!(motion.acceleration(), -2000.0);
+        assert_eq!(motion.direction(), Some(-std::f64::consts::FRAC_PI_2));
+        assert_eq!(motion.distance(), 80.0);
+
+        // Older samples are ignored
+        motion.update(&sample(start, 150, (0, 0)));
+        assert_eq!(motion.distance(), 80.0);
+
+        motion.reset();
+        assert_eq!(motion.distance(), 0.0);
+        assert_eq!(motion.speed(), 0.0);
+    }
+
+    #[test]
+    fn distance_mm_test() {
+        let start = Instant::now();
+        let mut motion = MotionTracker::new();
+        // 192 DPI across, 180 DPI down
+        let monitor =
+            Monitor::from("eDP-1", (0, 0), (2880, 1800), true, 2.0).with_physical_size((381, 254));
+
+        motion.update(&sample(start, 0, (0, 0)));
+        motion.update(&sample(start, 10, (96, 0)));
+        assert!((motion.distance_mm() - 25.4).abs() < 1e-9);
+
+        // The interface scale does not matter without a physical size
+        let unsized_monitor = Monitor::from("eDP-1", (0, 0), (2880, 1800), true, 2.0);
+        motion.update(&sample(start, 20, (192, 0)).with_monitor(unsized_monitor));
+        assert!((motion.distance_mm() - 50.8).abs() < 1e-9);
+
+        motion.update(&sample(start, 30, (384, 0)).with_monitor(monitor.clone()));
+        assert!((motion.distance_mm() - 76.2).abs() < 1e-9);
+        motion.update(&sample(start, 40, (384, 180)).with_monitor(monitor));
+        assert!((motion.distance_mm() - 101.6).abs() < 1e-9);
+    }
+}
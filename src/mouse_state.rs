@@ -1,36 +1,59 @@
+use crate::{timestamp::SampleTime, Monitor, Timestamp};
+
 /// A simple structure containing the current mouse coordinates and the
-/// state of each mouse button that we can query. Currently, Windows and
-/// Linux provide nice ways to query five mouse buttons.
+/// mouse buttons that are held. Mice with more than the usual five buttons
+/// report their extra buttons as `MouseButton::Other`.
 ///
-/// `buttons` will contain an array of the five mouse buttons, with `true` meaning that the button
-/// is pressed and `false` meaning that the button is not pressed. Index 0 is mouse button 1, Index 1 is button 2, etc.  
+/// `buttons` will contain the held buttons, sorted and without duplicates
 /// ```rust
-/// # fn example_buttons() -> Result<(), ()> {
-/// # use device_query::{DeviceQuery, DeviceState, MouseState};
-/// # let device_state = DeviceState::new();
+/// # fn example_buttons() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceQuery, DeviceState, MouseButton, MouseState};
+/// # let device_state = DeviceState::new()?;
 /// let mouse: MouseState = device_state.get_mouse(); // get_mouse() from the `DeviceQuery` trait
 /// println!("{:?}", mouse.buttons()); // Prints something along the lines of
-///                                    // `[false, true, false, false, false]`, depending on what buttons are
-///                                    // pressed. Index 0 is mouse button 1, Index 1 is button 2, etc.
+///                                    // `[Left, Back]`, depending on what buttons are pressed
+/// println!("{}", mouse.get_button(MouseButton::Left)); // Prints whether the left button is held
 /// # Ok(())
 /// # }
 /// ```
 ///
 /// `coordinates` will contain a tuple of the x and y coordinates of the cursor  
 /// ```rust
-/// # fn example_coordinates() -> Result<(), ()> {
+/// # fn example_coordinates() -> Result<(), Box<dyn std::error::Error>> {
 /// # use device_query::{DeviceQuery, DeviceState, MouseState};
-/// # let device_state = DeviceState::new();
+/// # let device_state = DeviceState::new()?;
 /// let mouse: MouseState = device_state.get_mouse(); // get_mouse() from the `DeviceQuery` trait
 /// println!("{:?}", mouse.coordinates()); // Prints something along the lines of `(100, 100)`, depending on
 ///                                        // where your mouse is
 /// # Ok(())
 /// # }
 /// ```
+///
+/// `monitor` is the monitor the cursor is on, which also gives the coordinates relative
+/// to it, when the DeviceState can tell
+/// ```rust
+/// # fn example_monitor() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceQuery, DeviceState, MouseState};
+/// # let device_state = DeviceState::new()?;
+/// let mouse: MouseState = device_state.get_mouse();
+/// if let (Some(monitor), Some(coordinates)) = (mouse.monitor(), mouse.monitor_coordinates()) {
+///     println!("{:?} on {}", coordinates, monitor.name()); // Prints something along the lines of
+///                                                          // `(100, 100) on DP-1`
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Wheel notches are never reported as button presses, `DeviceState::query_scroll_events()`
+/// reports the scrolling on Linux.
+///
+/// `timestamp` records when the state was sampled.
 #[derive(Debug, PartialEq, Clone)]
 pub struct MouseState {
     coordinates: (i32, i32),
-    buttons: [bool; 5],
+    buttons: Vec<MouseButton>,
+    monitor: Option<Monitor>,
+    timestamp: SampleTime,
 }
 
 impl MouseState {
@@ -39,62 +62,97 @@ impl MouseState {
         self.coordinates
     }
 
-    /// Get an array containing the state of the mouse buttons
-    pub fn buttons(&self) -> [bool; 5] {
-        self.buttons
+    /// Get the held mouse buttons, sorted
+    pub fn buttons(&self) -> &[MouseButton] {
+        &self.buttons
+    }
+
+    /// Get the monitor the cursor is on
+    pub fn monitor(&self) -> Option<&Monitor> {
+        self.monitor.as_ref()
     }
 
-    /// Get a mouse button
+    /// Get the coordinates of the cursor relative to the top left corner of its monitor
+    pub fn monitor_coordinates(&self) -> Option<(i32, i32)> {
+        self.monitor
+            .as_ref()
+            .map(|monitor| monitor.to_local(self.coordinates))
+    }
+
+    /// Get the moment the mouse state was sampled. Its server time is always `None`,
+    /// as the state is polled.
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+
+    /// Check whether a mouse button is held
     pub fn get_button(&self, button: MouseButton) -> bool {
-        match button {
-            MouseButton::Right => self.buttons[0],
-            MouseButton::Left => self.buttons[1],
-            MouseButton::Middle => self.buttons[2],
-            MouseButton::Four => self.buttons[3],
-            MouseButton::Five => self.buttons[4],
-        }
+        self.buttons.binary_search(&button).is_ok()
     }
 
     /// Get a vector of the currently activated MouseButtons
     pub fn get_buttons(&self) -> Vec<MouseButton> {
-        let mut buttons = Vec::with_capacity(5);
-        let codes = [
-            MouseButton::Right,
-            MouseButton::Left,
-            MouseButton::Middle,
-            MouseButton::Four,
-            MouseButton::Five,
-        ];
-
-        for (i, button) in (&self.buttons).iter().enumerate() {
-            if *button {
-                buttons.push(codes[i].clone());
-            }
-        }
-
-        buttons
+        self.buttons.clone()
     }
 
-    /// Create a MouseState from a coordinate tuple and a button array
-    pub fn from(coordinates: (i32, i32), buttons: [bool; 5]) -> Self {
+    /// Create a MouseState from a coordinate tuple and the held buttons, sampled now
+    pub fn from(coordinates: (i32, i32), buttons: &[MouseButton]) -> Self {
+        let mut buttons = buttons.to_vec();
+        buttons.sort();
+        buttons.dedup();
+
         Self {
             coordinates,
             buttons,
+            monitor: None,
+            timestamp: SampleTime(Timestamp::now()),
         }
     }
+
+    /// Set the monitor the cursor is on
+    pub fn with_monitor(mut self, monitor: Monitor) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Replace the moment the MouseState was sampled
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = SampleTime(timestamp);
+        self
+    }
 }
 
 /// Allows access to mouse buttons in a named way
 /// via MouseState::get_button()
-#[derive(PartialEq, Debug, Clone)]
+///
+/// Which physical button `Left` and `Right` stand for depends on the `ButtonMapping`
+/// of the DeviceState.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
 pub enum MouseButton {
-    Right,
+    /// The primary button, or button 1 on X11
     Left,
+    /// Button 2 on X11
     Middle,
-    /// Also known as XButton1
-    Four,
-    /// Also known as XButton2
-    Five,
+    /// The secondary button, or button 3 on X11
+    Right,
+    /// The first side button, also known as XButton1, or button 8 on X11
+    Back,
+    /// The second side button, also known as XButton2, or button 9 on X11
+    Forward,
+    /// Any other button, by the number the platform gives it, such as the
+    /// extra thumb buttons numbered from 10 on X11
+    Other(u8),
+}
+
+/// How the buttons of the mouse are named, set with `DeviceState::with_button_mapping()`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ButtonMapping {
+    /// Follow the system settings: for left-handed users, `MouseButton::Left` is the
+    /// primary button on the right of the mouse. This is the default.
+    #[default]
+    Logical,
+    /// Name the buttons after where they are on the mouse, whatever the system settings
+    Physical,
 }
 
 #[cfg(test)]
@@ -103,7 +161,7 @@ mod tests {
 
     #[test]
     fn coordinate_test() {
-        let test_mouse = MouseState::from((100, 100), [false; 5]);
+        let test_mouse = MouseState::from((100, 100), &[]);
 
         assert!(
             test_mouse.coordinates == test_mouse.coordinates()
@@ -114,37 +172,99 @@ mod tests {
 
     #[test]
     fn button_test() {
-        let test_mouse = MouseState::from((100, 100), [false; 5]);
+        let test_mouse = MouseState::from((100, 100), &[]);
 
-        assert!(
-            test_mouse.buttons == test_mouse.buttons()
-                && test_mouse.buttons == [false; 5]
-                && test_mouse.buttons() == [false; 5]
+        assert!(test_mouse.buttons().is_empty());
+
+        let test_mouse = MouseState::from(
+            (100, 100),
+            &[
+                MouseButton::Other(12),
+                MouseButton::Right,
+                MouseButton::Back,
+                MouseButton::Right,
+            ],
+        );
+
+        assert_eq!(
+            test_mouse.buttons(),
+            &[
+                MouseButton::Right,
+                MouseButton::Back,
+                MouseButton::Other(12)
+            ]
         );
     }
 
     #[test]
     fn named_button_test() {
-        let test_mouse = MouseState::from((100, 100), [true, false, true, false, true]);
+        let test_mouse = MouseState::from(
+            (100, 100),
+            &[
+                MouseButton::Right,
+                MouseButton::Middle,
+                MouseButton::Forward,
+                MouseButton::Other(10),
+            ],
+        );
 
-        assert_eq!(test_mouse.get_button(MouseButton::Right), true);
-        assert_eq!(test_mouse.get_button(MouseButton::Left), false);
-        assert_eq!(test_mouse.get_button(MouseButton::Middle), true);
-        assert_eq!(test_mouse.get_button(MouseButton::Four), false);
-        assert_eq!(test_mouse.get_button(MouseButton::Five), true);
+        assert!(test_mouse.get_button(MouseButton::Right));
+        assert!(!test_mouse.get_button(MouseButton::Left));
+        assert!(test_mouse.get_button(MouseButton::Middle));
+        assert!(!test_mouse.get_button(MouseButton::Back));
+        assert!(test_mouse.get_button(MouseButton::Forward));
+        assert!(test_mouse.get_button(MouseButton::Other(10)));
+        assert!(!test_mouse.get_button(MouseButton::Other(11)));
     }
 
     #[test]
     fn get_buttons_test() {
-        let test_mouse = MouseState::from((100, 100), [false; 5]);
+        let test_mouse = MouseState::from((100, 100), &[]);
 
         assert_eq!(test_mouse.get_buttons(), Vec::default());
 
-        let test_mouse = MouseState::from((100, 100), [true, false, true, false, true]);
+        let test_mouse = MouseState::from(
+            (100, 100),
+            &[
+                MouseButton::Forward,
+                MouseButton::Middle,
+                MouseButton::Right,
+            ],
+        );
 
         assert_eq!(
             test_mouse.get_buttons(),
-            vec![MouseButton::Right, MouseButton::Middle, MouseButton::Five]
+            vec![
+                MouseButton::Middle,
+                MouseButton::Right,
+                MouseButton::Forward
+            ]
         );
     }
+
+    #[test]
+    fn timestamp_test() {
+        let timestamp = Timestamp::now();
+        let test_mouse = MouseState::from((100, 100), &[]).with_timestamp(timestamp);
+
+        assert_eq!(test_mouse.timestamp(), timestamp);
+        assert_eq!(test_mouse, MouseState::from((100, 100), &[]));
+        assert_ne!(test_mouse, MouseState::from((100, 101), &[]));
+        assert_ne!(
+            test_mouse,
+            MouseState::from((100, 100), &[MouseButton::Left])
+        );
+    }
+
+    #[test]
+    fn monitor_test() {
+        let test_mouse = MouseState::from((2000, 100), &[]);
+        assert_eq!(test_mouse.monitor(), None);
+        assert_eq!(test_mouse.monitor_coordinates(), None);
+
+        let monitor = Monitor::from("DP-2", (1920, 0), (2560, 1440), false, 1.0);
+        let test_mouse = test_mouse.with_monitor(monitor.clone());
+        assert_eq!(test_mouse.monitor(), Some(&monitor));
+        assert_eq!(test_mouse.monitor_coordinates(), Some((80, 100)));
+    }
 }
@@ -0,0 +1,145 @@
+use crate::{timestamp::SampleTime, Timestamp};
+
+/// The end of the pen touching the tablet
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PenTool {
+    Pen,
+    Eraser,
+}
+
+/// The state of a graphics tablet pen, as returned by `DeviceState::query_pens()` on Linux.
+///
+/// The position, pressure and tilt are those of the last time the pen was near the
+/// tablet.
+/// ```rust
+/// # #[cfg(target_os = "linux")]
+/// # fn example_pens() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::DeviceState;
+/// # let device_state = DeviceState::new()?;
+/// for pen in device_state.query_pens() {
+///     if pen.is_touching() {
+///         println!("Drawing at {:?} with {}", pen.position(), pen.pressure());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct PenState {
+    device: u64,
+    name: String,
+    tool: PenTool,
+    position: (f64, f64),
+    pressure: f64,
+    tilt: (f64, f64),
+    barrel_buttons: Vec<u8>,
+    touching: bool,
+    in_proximity: bool,
+    timestamp: SampleTime,
+}
+
+impl PenState {
+    /// Get the id of the pen's device, as in `InputDevice::id()`
+    pub fn device(&self) -> u64 {
+        self.device
+    }
+
+    /// Get the name of the pen's device
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get which end of the pen is used
+    pub fn tool(&self) -> PenTool {
+        self.tool
+    }
+
+    /// Get the position of the pen on the screen, in pixels
+    pub fn position(&self) -> (f64, f64) {
+        self.position
+    }
+
+    /// Get how hard the pen presses, from 0.0 to 1.0
+    pub fn pressure(&self) -> f64 {
+        self.pressure
+    }
+
+    /// Get how far the pen leans, each axis going from -1.0 to 1.0 across the range
+    /// the tablet reports, positive to the right and towards the user. Tablets that
+    /// cannot tell report (0.0, 0.0).
+    pub fn tilt(&self) -> (f64, f64) {
+        self.tilt
+    }
+
+    /// Get the held buttons on the side of the pen, numbered from 1 for the one
+    /// closest to the tip, sorted
+    pub fn barrel_buttons(&self) -> &[u8] {
+        &self.barrel_buttons
+    }
+
+    /// Check whether the pen touches the tablet
+    pub fn is_touching(&self) -> bool {
+        self.touching
+    }
+
+    /// Check whether the pen is close enough to the tablet to be followed
+    pub fn in_proximity(&self) -> bool {
+        self.in_proximity
+    }
+
+    /// Get the moment the state was sampled
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+
+    /// Create a PenState from the id and name of its device, which end of the pen is
+    /// used and its position, in proximity, not touching and without pressure or tilt
+    pub fn from(device: u64, name: &str, tool: PenTool, position: (f64, f64)) -> Self {
+        Self {
+            device,
+            name: name.to_string(),
+            tool,
+            position,
+            pressure: 0.0,
+            tilt: (0.0, 0.0),
+            barrel_buttons: Vec::new(),
+            touching: false,
+            in_proximity: true,
+            timestamp: SampleTime(Timestamp::now()),
+        }
+    }
+
+    /// Set how hard the pen presses
+    pub fn with_pressure(mut self, pressure: f64) -> Self {
+        self.pressure = pressure;
+        self
+    }
+
+    /// Set how far the pen leans
+    pub fn with_tilt(mut self, tilt: (f64, f64)) -> Self {
+        self.tilt = tilt;
+        self
+    }
+
+    /// Set the held barrel buttons
+    pub fn with_barrel_buttons(mut self, buttons: &[u8]) -> Self {
+        let mut buttons = buttons.to_vec();
+        buttons.sort_unstable();
+        buttons.dedup();
+        self.barrel_buttons = buttons;
+        self
+    }
+
+    /// Set whether the pen touches the tablet and whether it is in proximity
+    pub fn with_contact(mut self, touching: bool, in_proximity: bool) -> Self {
+        self.touching = touching;
+        self.in_proximity = in_proximity;
+        self
+    }
+
+    /// Set the moment the state was sampled
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = SampleTime(timestamp);
+        self
+    }
+}
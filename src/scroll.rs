@@ -0,0 +1,114 @@
+use crate::Timestamp;
+use std::ops::{Add, AddAssign};
+
+/// An amount of scrolling, in wheel notches.
+///
+/// Positive `vertical` values scroll down and positive `horizontal` values scroll right.
+/// Smooth scrolling devices, such as touchpads, report fractions of a notch.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct ScrollDelta {
+    horizontal: f64,
+    vertical: f64,
+}
+
+impl ScrollDelta {
+    /// Get the horizontal scrolling, positive to the right
+    pub fn horizontal(&self) -> f64 {
+        self.horizontal
+    }
+
+    /// Get the vertical scrolling, positive downwards
+    pub fn vertical(&self) -> f64 {
+        self.vertical
+    }
+
+    /// Check whether there was no scrolling at all
+    pub fn is_zero(&self) -> bool {
+        self.horizontal == 0.0 && self.vertical == 0.0
+    }
+
+    /// Create a ScrollDelta from horizontal and vertical notches
+    pub fn from(horizontal: f64, vertical: f64) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+impl Add for ScrollDelta {
+    type Output = ScrollDelta;
+
+    fn add(self, other: ScrollDelta) -> ScrollDelta {
+        ScrollDelta::from(
+            self.horizontal + other.horizontal,
+            self.vertical + other.vertical,
+        )
+    }
+}
+
+impl AddAssign for ScrollDelta {
+    fn add_assign(&mut self, other: ScrollDelta) {
+        *self = *self + other;
+    }
+}
+
+/// A single wheel notch or smooth scrolling step, as reported by
+/// `DeviceState::query_scroll_events()` on Linux
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScrollEvent {
+    delta: ScrollDelta,
+    timestamp: Timestamp,
+    device: Option<u64>,
+}
+
+impl ScrollEvent {
+    /// Get how much the step scrolled
+    pub fn delta(&self) -> ScrollDelta {
+        self.delta
+    }
+
+    /// Get the moment the step happened
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Get the id of the physical device that produced the event, as in
+    /// `InputDevice::id()`, if known
+    pub fn device(&self) -> Option<u64> {
+        self.device
+    }
+
+    /// Create a ScrollEvent from a delta and the moment it happened
+    pub fn from(delta: ScrollDelta, timestamp: Timestamp) -> Self {
+        Self {
+            delta,
+            timestamp,
+            device: None,
+        }
+    }
+
+    /// Set the id of the physical device that produced the event
+    pub fn with_device(mut self, device: u64) -> Self {
+        self.device = Some(device);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_test() {
+        let mut delta = ScrollDelta::default();
+        assert!(delta.is_zero());
+
+        delta += ScrollDelta::from(0.0, -1.0);
+        delta += ScrollDelta::from(0.5, 0.25);
+        assert_eq!(delta, ScrollDelta::from(0.5, -0.75));
+        assert_eq!(delta.horizontal(), 0.5);
+        assert_eq!(delta.vertical(), -0.75);
+        assert!(!delta.is_zero());
+    }
+}
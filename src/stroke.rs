@@ -0,0 +1,332 @@
+use crate::{DeviceQuery, MouseButton, MouseState, Timestamp};
+
+/// How many points paths are resampled to before being compared
+const RESAMPLED_POINTS: usize = 32;
+
+/// A path drawn while the trigger button was held
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stroke {
+    path: Vec<(i32, i32)>,
+    directions: String,
+    template: Option<String>,
+    timestamp: Timestamp,
+}
+
+impl Stroke {
+    /// Get the cursor positions sampled while the button was held
+    pub fn path(&self) -> &[(i32, i32)] {
+        &self.path
+    }
+
+    /// Get the successive directions of the stroke, made of `L`, `R`, `U` and `D`
+    /// for left, right, up and down, such as `LDR`
+    pub fn directions(&self) -> &str {
+        &self.directions
+    }
+
+    /// Get the name of the template the stroke looks the most like, if any is close enough
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+
+    /// Get the moment the button was released
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+}
+
+/// Records the cursor path while a trigger button is held and recognizes it
+/// once the button is released, like the mouse gestures of web browsers.
+///
+/// Strokes are classified into direction strings: a direction is added each time
+/// the cursor travels the segment length, 20 pixels by default, mostly along one
+/// axis in a different direction than the previous one. They are also compared with
+/// user-defined templates, whatever their size and position on the screen.
+/// ```rust
+/// # fn example_strokes() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceState, StrokeRecognizer};
+/// # let device_state = DeviceState::new()?;
+/// let mut strokes = StrokeRecognizer::new();
+/// strokes.add_template("check", &[(0, 0), (10, 10), (30, -20)]);
+///
+/// loop {
+///     if let Some(stroke) = strokes.poll(&device_state) {
+///         match (stroke.directions(), stroke.template()) {
+///             ("L", _) => println!("Back"),
+///             (_, Some("check")) => println!("Done"),
+///             _ => {}
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct StrokeRecognizer {
+    trigger: MouseButton,
+    segment_length: i32,
+    templates: Vec<(String, Vec<(f64, f64)>)>,
+    path: Option<Vec<(i32, i32)>>,
+}
+
+impl Default for StrokeRecognizer {
+    fn default() -> Self {
+        Self {
+            trigger: MouseButton::Right,
+            segment_length: 20,
+            templates: Vec::new(),
+            path: None,
+        }
+    }
+}
+
+impl StrokeRecognizer {
+    /// Create a StrokeRecognizer triggered by the right button, without templates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the button to hold while drawing strokes
+    pub fn with_trigger(mut self, trigger: MouseButton) -> Self {
+        self.trigger = trigger;
+        self.path = None;
+        self
+    }
+
+    /// Set how far, in pixels, the cursor has to travel to add a direction
+    pub fn with_segment_length(mut self, segment_length: i32) -> Self {
+        self.segment_length = segment_length;
+        self
+    }
+
+    /// Add a template to compare strokes with. Templates without any length are ignored.
+    pub fn add_template(&mut self, name: &str, path: &[(i32, i32)]) {
+        if let Some(points) = normalize(path) {
+            self.templates.push((name.to_string(), points));
+        }
+    }
+
+    /// Remove the templates with a name, returning whether there was any
+    pub fn remove_template(&mut self, name: &str) -> bool {
+        let count = self.templates.len();
+        self.templates.retain(|(template, _)| template != name);
+        self.templates.len() != count
+    }
+
+    /// Check whether a stroke is being drawn
+    pub fn is_recording(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Query the mouse and return the stroke finished since the previous update
+    pub fn poll<D: DeviceQuery>(&mut self, device_state: &D) -> Option<Stroke> {
+        self.update(&device_state.get_mouse())
+    }
+
+    /// Record the cursor position and return the stroke if the trigger button was
+    /// just released. Strokes without any direction are dropped, as they are clicks.
+    pub fn update(&mut self, mouse: &MouseState) -> Option<Stroke> {
+        let coordinates = mouse.coordinates();
+
+        if mouse.get_button(self.trigger) {
+            let path = self.path.get_or_insert_with(Vec::new);
+            if path.last() != Some(&coordinates) {
+                path.push(coordinates);
+            }
+            return None;
+        }
+
+        let path = self.path.take()?;
+        let directions = self.directions(&path);
+        if directions.is_empty() {
+            return None;
+        }
+
+        Some(Stroke {
+            template: self.best_template(&path),
+            path,
+            directions,
+            timestamp: mouse.timestamp(),
+        })
+    }
+
+    /// Classify a path into directions
+    fn directions(&self, path: &[(i32, i32)]) -> String {
+        let mut directions = String::new();
+        let mut anchor = match path.first() {
+            Some(first) => *first,
+            None => return directions,
+        };
+
+        for point in path {
+            let (dx, dy) = (point.0 - anchor.0, point.1 - anchor.1);
+            if dx.abs().max(dy.abs()) < self.segment_length.max(1) {
+                continue;
+            }
+
+            let direction = match (dx.abs() >= dy.abs(), dx < 0, dy < 0) {
+                (true, true, _) => 'L',
+                (true, false, _) => 'R',
+                (false, _, true) => 'U',
+                (false, _, false) => 'D',
+            };
+            if !directions.ends_with(direction) {
+                directions.push(direction);
+            }
+            anchor = *point;
+        }
+
+        directions
+    }
+
+    /// Find the closest template to a path, if it is close enough
+    fn best_template(&self, path: &[(i32, i32)]) -> Option<String> {
+        let points = normalize(path)?;
+
+        self.templates
+            .iter()
+            .map(|(name, template)| {
+                let distance = points
+                    .iter()
+                    .zip(template)
+                    .map(|(a, b)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt())
+                    .sum::<f64>()
+                    / RESAMPLED_POINTS as f64;
+                (name, distance)
+            })
+            .filter(|(_, distance)| *distance < 0.15)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// Resample a path to evenly spaced points, center it on its centroid and scale it
+/// so that its largest side is 1, keeping its aspect ratio. `None` for a single point.
+fn normalize(path: &[(i32, i32)]) -> Option<Vec<(f64, f64)>> {
+    let path: Vec<(f64, f64)> = path.iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+    let distance =
+        |a: (f64, f64), b: (f64, f64)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+
+    let length: f64 = path.windows(2).map(|w| distance(w[0], w[1])).sum();
+    if length == 0.0 {
+        return None;
+    }
+
+    // Walk the path, dropping a point every `interval`
+    let interval = length / (RESAMPLED_POINTS - 1) as f64;
+    let mut points = vec![path[0]];
+    let mut walked = 0.0;
+    for segment in path.windows(2) {
+        let (mut start, end) = (segment[0], segment[1]);
+        let mut segment_length = distance(start, end);
+
+        while walked + segment_length >= interval && points.len() < RESAMPLED_POINTS {
+            let t = (interval - walked) / segment_length;
+            start = (
+                start.0 + t * (end.0 - start.0),
+                start.1 + t * (end.1 - start.1),
+            );
+            points.push(start);
+            segment_length = distance(start, end);
+            walked = 0.0;
+        }
+        walked += segment_length;
+    }
+    // Rounding errors may leave the last point out
+    while points.len() < RESAMPLED_POINTS {
+        points.push(path[path.len() - 1]);
+    }
+
+    let (min_x, max_x, min_y, max_y) = points.iter().fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(min_x, max_x, min_y, max_y), p| {
+            (
+                min_x.min(p.0),
+                max_x.max(p.0),
+                min_y.min(p.1),
+                max_y.max(p.1),
+            )
+        },
+    );
+    let scale = (max_x - min_x).max(max_y - min_y);
+    let centroid = points
+        .iter()
+        .fold((0.0, 0.0), |sum, p| (sum.0 + p.0, sum.1 + p.1));
+    let centroid = (
+        centroid.0 / RESAMPLED_POINTS as f64,
+        centroid.1 / RESAMPLED_POINTS as f64,
+    );
+
+    Some(
+        points
+            .into_iter()
+            .map(|p| ((p.0 - centroid.0) / scale, (p.1 - centroid.1) / scale))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draw a path holding the right button, then release it
+    fn draw(strokes: &mut StrokeRecognizer, path: &[(i32, i32)]) -> Option<Stroke> {
+        for point in path {
+            let mouse = MouseState::from(*point, &[MouseButton::Right]);
+            assert_eq!(strokes.update(&mouse), None);
+        }
+        strokes.update(&MouseState::from(path[path.len() - 1], &[]))
+    }
+
+    #[test]
+    fn directions_test() {
+        let mut strokes = StrokeRecognizer::new();
+
+        let stroke = draw(
+            &mut strokes,
+            &[
+                (100, 100),
+                (90, 102),
+                (70, 101),
+                (71, 130),
+                (69, 160),
+                (100, 158),
+            ],
+        )
+        .unwrap();
+        assert_eq!(stroke.directions(), "LDR");
+        assert_eq!(stroke.path().len(), 6);
+        assert!(!strokes.is_recording());
+
+        let stroke = draw(&mut strokes, &[(0, 0), (0, -25), (0, -50), (0, -75)]).unwrap();
+        assert_eq!(stroke.directions(), "U");
+    }
+
+    #[test]
+    fn click_test() {
+        let mut strokes = StrokeRecognizer::new();
+
+        assert_eq!(draw(&mut strokes, &[(100, 100), (105, 95)]), None);
+    }
+
+    #[test]
+    fn template_test() {
+        let mut strokes = StrokeRecognizer::new();
+        strokes.add_template("check", &[(0, 0), (10, 10), (30, -20)]);
+        strokes.add_template("line", &[(0, 0), (100, 0)]);
+        strokes.add_template("dot", &[(0, 0)]);
+
+        // The same shapes, bigger and elsewhere on the screen
+        let check = draw(&mut strokes, &[(500, 500), (540, 540), (620, 420)]).unwrap();
+        assert_eq!(check.template(), Some("check"));
+        let line = draw(&mut strokes, &[(10, 300), (60, 301), (210, 300)]).unwrap();
+        assert_eq!(line.template(), Some("line"));
+
+        let square = draw(
+            &mut strokes,
+            &[(0, 0), (100, 0), (100, 100), (0, 100), (0, 0)],
+        );
+        assert_eq!(square.unwrap().template(), None);
+
+        assert!(strokes.remove_template("line"));
+        assert!(!strokes.remove_template("dot"));
+    }
+}
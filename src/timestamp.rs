@@ -0,0 +1,89 @@
+use std::{fmt, time::Instant};
+
+/// The moment a piece of device state was sampled.
+///
+/// `instant` is a monotonic clock reading taken by this process, suitable for
+/// measuring intervals and correlating with other `Instant`s. `server_time`
+/// is the time the display server attached to the input, in milliseconds,
+/// when the state came from a server event. It wraps around roughly every
+/// 49.7 days.
+///
+/// Only the events drained by the `query_*_events()` methods of `DeviceState`
+/// carry a server time, and only on Linux. Polled state such as `MouseState`
+/// and `DeviceSnapshot` always has `None`, and the keys of `get_keys()` have no
+/// timestamp at all: use `get_state()` to know when the keys were sampled.
+///
+/// Sampled states such as `MouseState`, `DeviceSnapshot` or `GamepadState` compare
+/// equal whatever their timestamps, so comparing consecutive samples tells whether
+/// the devices changed.
+/// ```rust
+/// # fn example_timestamp() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::{DeviceQuery, DeviceState};
+/// # let device_state = DeviceState::new()?;
+/// let mouse = device_state.get_mouse();
+/// println!("sampled {:?} ago", mouse.timestamp().instant().elapsed());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Timestamp {
+    instant: Instant,
+    server_time: Option<u32>,
+}
+
+impl Timestamp {
+    /// Create a Timestamp for the current moment
+    pub fn now() -> Self {
+        Self::from(Instant::now(), None)
+    }
+
+    /// Get the monotonic clock reading
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Get the display server time in milliseconds, if known
+    pub fn server_time(&self) -> Option<u32> {
+        self.server_time
+    }
+
+    /// Create a Timestamp from a clock reading and an optional server time
+    pub fn from(instant: Instant, server_time: Option<u32>) -> Self {
+        Self {
+            instant,
+            server_time,
+        }
+    }
+}
+
+/// The Timestamp of a sampled state, which compares equal to any other so that the
+/// states can derive PartialEq and compare by their contents
+#[derive(Clone, Copy)]
+pub(crate) struct SampleTime(pub(crate) Timestamp);
+
+impl PartialEq for SampleTime {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for SampleTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn sample_time_test() {
+        let instant = Instant::now();
+        let earlier = SampleTime(Timestamp::from(instant, None));
+        let later = SampleTime(Timestamp::from(instant + Duration::from_millis(5), Some(7)));
+        assert_eq!(earlier, later);
+        assert_eq!(format!("{:?}", earlier), format!("{:?}", earlier.0));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{timestamp::SampleTime, Timestamp};
+
+/// A finger on a touchscreen, as listed by `TouchState::touches()`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TouchPoint {
+    id: u64,
+    device: Option<u64>,
+    position: (f64, f64),
+    pressure: Option<f64>,
+    size: Option<(f64, f64)>,
+}
+
+impl TouchPoint {
+    /// Get the identifier of the touch, which stays the same from the moment the
+    /// finger lands until it is lifted
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the id of the touchscreen, as in `InputDevice::id()`, if known
+    pub fn device(&self) -> Option<u64> {
+        self.device
+    }
+
+    /// Get the position of the touch on the screen, in pixels
+    pub fn position(&self) -> (f64, f64) {
+        self.position
+    }
+
+    /// Get how hard the finger presses, from 0.0 to 1.0, `None` if the touchscreen
+    /// cannot tell
+    pub fn pressure(&self) -> Option<f64> {
+        self.pressure
+    }
+
+    /// Get the size of the contact area as its longest and shortest extents, in
+    /// pixels, `None` if the touchscreen cannot tell
+    pub fn size(&self) -> Option<(f64, f64)> {
+        self.size
+    }
+
+    /// Create a TouchPoint from its identifier and position
+    pub fn from(id: u64, position: (f64, f64)) -> Self {
+        Self {
+            id,
+            device: None,
+            position,
+            pressure: None,
+            size: None,
+        }
+    }
+
+    /// Set the id of the touchscreen
+    pub fn with_device(mut self, device: u64) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Set how hard the finger presses
+    pub fn with_pressure(mut self, pressure: f64) -> Self {
+        self.pressure = Some(pressure);
+        self
+    }
+
+    /// Set the longest and shortest extents of the contact area
+    pub fn with_size(mut self, size: (f64, f64)) -> Self {
+        self.size = Some(size);
+        self
+    }
+}
+
+/// The fingers on the touchscreens, as returned by `DeviceState::query_touches()` on Linux.
+///
+/// Unlike MouseState, which only follows the touch the system turns into pointer
+/// motion, every finger is listed, in the order they landed.
+/// ```rust
+/// # #[cfg(target_os = "linux")]
+/// # fn example_touches() -> Result<(), Box<dyn std::error::Error>> {
+/// # use device_query::DeviceState;
+/// # let device_state = DeviceState::new()?;
+/// let touches = device_state.query_touches();
+/// for touch in touches.touches() {
+///     println!("Finger {} at {:?}", touch.id(), touch.position());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct TouchState {
+    touches: Vec<TouchPoint>,
+    timestamp: SampleTime,
+}
+
+impl TouchState {
+    /// Get the touches, in the order they started
+    pub fn touches(&self) -> &[TouchPoint] {
+        &self.touches
+    }
+
+    /// Get the touch with this identifier, `None` if it is not on the screen
+    pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touches.iter().find(|touch| touch.id == id)
+    }
+
+    /// Check whether no finger is on the screen
+    pub fn is_empty(&self) -> bool {
+        self.touches.is_empty()
+    }
+
+    /// Get the moment the state was sampled
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+
+    /// Create a TouchState from the touches on the screen, sampled now
+    pub fn from(touches: &[TouchPoint]) -> Self {
+        Self {
+            touches: touches.to_vec(),
+            timestamp: SampleTime(Timestamp::now()),
+        }
+    }
+
+    /// Set the moment the state was sampled
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = SampleTime(timestamp);
+        self
+    }
+}
@@ -0,0 +1,128 @@
+use crate::Timestamp;
+
+/// A top-level window and where it is on the desktop
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Window {
+    id: u64,
+    position: (i32, i32),
+    size: (u32, u32),
+}
+
+impl Window {
+    /// Get the platform's identifier of the window: the XID on Linux, the HWND on Windows
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the desktop coordinates of the top left corner of the window
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    /// Get the width and height of the window in pixels
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Check whether a point of the desktop is inside the window
+    pub fn contains(&self, coordinates: (i32, i32)) -> bool {
+        let (x, y) = self.to_local(coordinates);
+
+        x >= 0 && y >= 0 && (x as u32) < self.size.0 && (y as u32) < self.size.1
+    }
+
+    /// Convert desktop coordinates to coordinates relative to the top left corner of the window
+    pub fn to_local(&self, coordinates: (i32, i32)) -> (i32, i32) {
+        (
+            coordinates.0 - self.position.0,
+            coordinates.1 - self.position.1,
+        )
+    }
+
+    /// Create a Window from its identifier, position and size
+    pub fn from(id: u64, position: (i32, i32), size: (u32, u32)) -> Self {
+        Self { id, position, size }
+    }
+}
+
+/// The window that has the keyboard focus, as returned by `DeviceState::get_focused_window()`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FocusedWindow {
+    id: u64,
+    title: String,
+    class: String,
+    pid: Option<u32>,
+}
+
+impl FocusedWindow {
+    /// Get the platform's identifier of the window, the same as `Window::id()`
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the title of the window
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Get the class of the window, which names the application on Linux, such as
+    /// `XTerm`, and the window class on Windows, such as `Notepad`
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// Get the id of the process that owns the window, if known
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Create a FocusedWindow from its identifier, title, class and process id
+    pub fn from(id: u64, title: &str, class: &str, pid: Option<u32>) -> Self {
+        Self {
+            id,
+            title: title.to_string(),
+            class: class.to_string(),
+            pid,
+        }
+    }
+}
+
+/// A change of the focused window, as reported by `DeviceState::query_focus_events()`
+#[derive(Debug, PartialEq, Clone)]
+pub struct FocusEvent {
+    window: Option<FocusedWindow>,
+    timestamp: Timestamp,
+}
+
+impl FocusEvent {
+    /// Get the window that gained the focus, `None` if no window has it anymore
+    pub fn window(&self) -> Option<&FocusedWindow> {
+        self.window.as_ref()
+    }
+
+    /// Get the moment the focus changed
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Create a FocusEvent from the window that gained the focus and the moment it did
+    pub fn from(window: Option<FocusedWindow>, timestamp: Timestamp) -> Self {
+        Self { window, timestamp }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_test() {
+        let window = Window::from(0x1e00007, (100, 50), (640, 480));
+
+        assert_eq!(window.to_local((150, 40)), (50, -10));
+        assert!(!window.contains((150, 40)));
+        assert!(window.contains((100, 50)));
+        assert!(window.contains((739, 529)));
+        assert!(!window.contains((740, 529)));
+    }
+}
@@ -1,18 +1,381 @@
-use crate::{KeyCode, MouseState};
-use winapi::{shared::windef::POINT, um::winuser};
+use super::{shcore::ShellScaling, xinput::XInput};
+use crate::{
+    ButtonMapping, ClickSettings, CursorError, CursorEvent, CursorImage, DeviceEvent, DeviceKind,
+    DeviceSnapshot, FocusEvent, FocusedWindow, GamepadButton, GamepadState, InputDevice, KeyCode,
+    Modifier, Modifiers, Monitor, MouseButton, MouseState, Timestamp, Window,
+};
+use std::{
+    cell::{Cell, RefCell},
+    io, mem,
+    os::raw::c_int,
+    ptr,
+    time::Duration,
+};
+use winapi::{
+    shared::{
+        minwindef::{BOOL, DWORD, LPARAM, TRUE, UINT, WORD},
+        windef::{HBITMAP, HCURSOR, HDC, HMONITOR, HWND, LPRECT, POINT, RECT},
+    },
+    um::{wingdi, winnt::HANDLE, winuser, xinput},
+};
 
 /// The base struct for getting Mouse and Keyboard information,
 /// extra methods provided by DeviceQuery
-pub struct DeviceState;
+pub struct DeviceState {
+    button_mapping: ButtonMapping,
+    focused_window: Cell<Option<u64>>,
+    confined: Cell<bool>,
+    cursor: Cell<Option<u64>>,
+    devices: RefCell<Vec<InputDevice>>,
+    shell_scaling: Option<ShellScaling>,
+    xinput: Option<XInput>,
+    gamepads: RefCell<Vec<u64>>,
+}
 
 impl DeviceState {
     /// Create a new DeviceState
-    pub fn new() -> DeviceState {
-        DeviceState {}
+    pub fn new() -> Result<DeviceState, io::Error> {
+        let device_state = DeviceState {
+            button_mapping: ButtonMapping::default(),
+            focused_window: Cell::new(foreground_window()),
+            confined: Cell::new(false),
+            cursor: Cell::new(current_cursor()),
+            devices: RefCell::new(unsafe { raw_input_devices() }),
+            shell_scaling: ShellScaling::load(),
+            xinput: XInput::load(),
+            gamepads: RefCell::new(Vec::new()),
+        };
+        let gamepads = device_state.query_gamepads();
+        device_state
+            .gamepads
+            .replace(gamepads.iter().map(|gamepad| gamepad.id()).collect());
+
+        Ok(device_state)
+    }
+
+    /// Set how the mouse buttons are named. The default `ButtonMapping::Logical` follows
+    /// the "Switch primary and secondary buttons" setting.
+    pub fn with_button_mapping(mut self, button_mapping: ButtonMapping) -> Self {
+        self.button_mapping = button_mapping;
+        self
     }
 
     /// Query the mouse for it's coordinates and pressed buttons, returned as a MouseState
     pub fn query_mouse(&self) -> MouseState {
+        let timestamp = Timestamp::now();
+
+        self.mouse_state(
+            |key| unsafe { winuser::GetAsyncKeyState(key) as u32 & 0x8000 != 0 },
+            timestamp,
+        )
+    }
+
+    /// Query the gamepads, returned as GamepadStates ordered by id. They are read
+    /// through XInput, which reports up to four Xbox-style controllers, and are
+    /// always empty when no XInput library is installed.
+    pub fn query_gamepads(&self) -> Vec<GamepadState> {
+        let timestamp = Timestamp::now();
+        let xinput = match &self.xinput {
+            Some(xinput) => xinput,
+            None => return Vec::new(),
+        };
+
+        (0..xinput::XUSER_MAX_COUNT)
+            .filter_map(|index| {
+                let state = xinput.state(index)?;
+
+                Some(gamepad_state(index, &state.Gamepad).with_timestamp(timestamp))
+            })
+            .collect()
+    }
+
+    /// Get the monitors of the desktop
+    pub fn query_monitors(&self) -> Vec<Monitor> {
+        let mut monitors: Vec<HMONITOR> = Vec::new();
+
+        unsafe {
+            winuser::EnumDisplayMonitors(
+                ptr::null_mut(),
+                ptr::null(),
+                Some(push_monitor),
+                &mut monitors as *mut Vec<HMONITOR> as LPARAM,
+            );
+        }
+
+        monitors
+            .into_iter()
+            .filter_map(|monitor| self.describe_monitor(monitor))
+            .collect()
+    }
+
+    /// Get the monitor showing a point of the desktop, `None` if the point is
+    /// between or beyond the monitors
+    pub fn monitor_at(&self, x: i32, y: i32) -> Option<Monitor> {
+        let monitor =
+            unsafe { winuser::MonitorFromPoint(POINT { x, y }, winuser::MONITOR_DEFAULTTONULL) };
+        if monitor.is_null() {
+            return None;
+        }
+
+        self.describe_monitor(monitor)
+    }
+
+    /// Describe a monitor with its name, geometry, scale and physical size, `None` if it
+    /// is gone
+    fn describe_monitor(&self, monitor: HMONITOR) -> Option<Monitor> {
+        let mut info: winuser::MONITORINFOEXW = unsafe { mem::zeroed() };
+        info.cbSize = mem::size_of::<winuser::MONITORINFOEXW>() as u32;
+        let info_ptr = &mut info as *mut _ as winuser::LPMONITORINFO;
+        if unsafe { winuser::GetMonitorInfoW(monitor, info_ptr) } == 0 {
+            return None;
+        }
+
+        let name_len = info
+            .szDevice
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(info.szDevice.len());
+        let name = String::from_utf16_lossy(&info.szDevice[..name_len]);
+
+        // Before Windows 8.1, every monitor has the scale of 1
+        let scale = self
+            .shell_scaling
+            .as_ref()
+            .map_or(1.0, |shell_scaling| shell_scaling.scale(monitor));
+
+        let rect = info.rcMonitor;
+        let size = (
+            (rect.right - rect.left).max(0) as u32,
+            (rect.bottom - rect.top).max(0) as u32,
+        );
+        let physical_size = self
+            .shell_scaling
+            .as_ref()
+            .and_then(|shell_scaling| shell_scaling.physical_size(monitor, size));
+        let monitor = Monitor::from(
+            &name,
+            (rect.left, rect.top),
+            size,
+            info.dwFlags & winuser::MONITORINFOF_PRIMARY != 0,
+            scale,
+        );
+
+        match physical_size {
+            Some(physical_size) => Some(monitor.with_physical_size(physical_size)),
+            None => Some(monitor),
+        }
+    }
+
+    /// Get the top-level window under the cursor, `None` over the desktop background
+    pub fn window_under_cursor(&self) -> Option<Window> {
+        unsafe {
+            let mut point = POINT { x: 0, y: 0 };
+            if winuser::GetCursorPos(&mut point) == 0 {
+                return None;
+            }
+
+            let child = winuser::WindowFromPoint(point);
+            if child.is_null() {
+                return None;
+            }
+
+            let window = winuser::GetAncestor(child, winuser::GA_ROOT);
+            if window.is_null() || window == winuser::GetShellWindow() {
+                return None;
+            }
+
+            self.query_window(window as usize as u64)
+        }
+    }
+
+    /// Get where a window is, to convert desktop coordinates to coordinates relative
+    /// to it with `Window::to_local()`. `None` if the window does not exist.
+    pub fn query_window(&self, id: u64) -> Option<Window> {
+        let hwnd = id as usize as HWND;
+        let mut rect: RECT = unsafe { mem::zeroed() };
+
+        if unsafe { winuser::IsWindow(hwnd) == 0 || winuser::GetWindowRect(hwnd, &mut rect) == 0 } {
+            return None;
+        }
+
+        Some(Window::from(
+            id,
+            (rect.left, rect.top),
+            (
+                (rect.right - rect.left).max(0) as u32,
+                (rect.bottom - rect.top).max(0) as u32,
+            ),
+        ))
+    }
+
+    /// Query the keyboard for all pressed keys, returned as a vector of KeyCodes
+    pub fn query_keymap(&self) -> Vec<KeyCode> {
+        pressed_keys(&held_virtual_keys())
+    }
+
+    /// Query the keys and buttons held on a single physical device. Windows only keeps
+    /// the state of every device combined, so this is always `None` for now.
+    pub fn query_device_state(&self, _device: u64) -> Option<DeviceSnapshot> {
+        None
+    }
+
+    /// List the input devices Windows reports raw input for, named by their device
+    /// path. Windows has no master devices, so every device stands alone.
+    pub fn devices(&self) -> Vec<InputDevice> {
+        unsafe { raw_input_devices() }
+    }
+
+    /// Get the devices plugged in and unplugged since the previous query, then the
+    /// XInput controllers connected and disconnected. The devices of the previous query
+    /// are compared with the current ones, so a device unplugged and plugged back in
+    /// between two queries is not reported.
+    pub fn query_device_events(&self) -> Vec<DeviceEvent> {
+        let timestamp = Timestamp::now();
+        let devices = unsafe { raw_input_devices() };
+        let previous = self.devices.replace(devices.clone());
+        let gamepads = self.query_gamepads();
+        let previous_gamepads = self
+            .gamepads
+            .replace(gamepads.iter().map(|gamepad| gamepad.id()).collect());
+
+        let mut changes = device_changes(&previous, devices, timestamp);
+        changes.extend(gamepad_changes(&previous_gamepads, &gamepads, timestamp));
+        changes
+    }
+
+    /// Get the window that has the keyboard focus with its title, class and process id,
+    /// to scope hotkeys or recordings to an application. `None` when no window has
+    /// the focus.
+    pub fn get_focused_window(&self) -> Option<FocusedWindow> {
+        foreground_window().and_then(|id| unsafe { window_info(id as usize as HWND) })
+    }
+
+    /// Get the change of the focused window since the previous query, if any. Windows
+    /// only reports focus changes through event hooks, so the foreground window is
+    /// compared with the one of the previous query instead, and quick successive
+    /// changes are reported as one.
+    pub fn query_focus_events(&self) -> Vec<FocusEvent> {
+        let timestamp = Timestamp::now();
+        let focused = foreground_window();
+        if focused == self.focused_window.replace(focused) {
+            return Vec::new();
+        }
+
+        let window = focused.and_then(|id| unsafe { window_info(id as usize as HWND) });
+        vec![FocusEvent::from(window, timestamp)]
+    }
+
+    /// Move the cursor to a point of the desktop
+    pub fn set_mouse_position(&self, x: i32, y: i32) {
+        unsafe {
+            winuser::SetCursorPos(x, y);
+        }
+    }
+
+    /// Keep the cursor inside a region of the desktop until `release_cursor()` is
+    /// called, moving it into the region if needed. Windows releases the cursor by
+    /// itself when another application confines it.
+    pub fn confine_cursor(
+        &self,
+        position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), CursorError> {
+        let rect = RECT {
+            left: position.0,
+            top: position.1,
+            right: position
+                .0
+                .saturating_add(size.0.min(i32::MAX as u32) as i32),
+            bottom: position
+                .1
+                .saturating_add(size.1.min(i32::MAX as u32) as i32),
+        };
+        if rect.right <= rect.left
+            || rect.bottom <= rect.top
+            || unsafe { winuser::ClipCursor(&rect) } == 0
+        {
+            return Err(CursorError::InvalidRegion(position, size));
+        }
+
+        self.confined.set(true);
+        Ok(())
+    }
+
+    /// Let the cursor leave the region set with `confine_cursor()`, returning whether
+    /// it was confined
+    pub fn release_cursor(&self) -> bool {
+        if !self.confined.replace(false) {
+            return false;
+        }
+
+        unsafe {
+            winuser::ClipCursor(ptr::null());
+        }
+        true
+    }
+
+    /// Hide the cursor on every window. Windows only lets an application hide the
+    /// cursor over its own windows, so this does nothing and returns false.
+    pub fn hide_cursor(&self) -> bool {
+        false
+    }
+
+    /// Show the cursor hidden with `hide_cursor()` again, which never hides it on Windows
+    pub fn show_cursor(&self) -> bool {
+        false
+    }
+
+    /// Get the picture of the cursor, to draw it over a screen capture at the
+    /// `query_mouse()` coordinates minus the hotspot. Cursors do not have names on
+    /// Windows, and the serial is the handle of the cursor.
+    pub fn get_cursor_image(&self) -> Option<CursorImage> {
+        let cursor = current_cursor()?;
+
+        unsafe { cursor_image(cursor as usize as HCURSOR) }
+    }
+
+    /// Get the change of the cursor picture since the previous query, if any. The
+    /// cursor of the previous query is compared with the current one, so quick
+    /// successive changes are reported as one.
+    pub fn query_cursor_events(&self) -> Vec<CursorEvent> {
+        let timestamp = Timestamp::now();
+        match current_cursor() {
+            Some(cursor) if Some(cursor) != self.cursor.replace(Some(cursor)) => {
+                vec![CursorEvent::from(cursor, "", timestamp)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Query the keyboard and the mouse together, returned as a DeviceSnapshot. The
+    /// keys, mouse buttons and held modifiers come from a single sample of the keys.
+    pub fn query_state(&self) -> DeviceSnapshot {
+        let timestamp = Timestamp::now();
+        let held = held_virtual_keys();
+        let keys = pressed_keys(&held);
+        let mouse = self.mouse_state(|key| held[key as usize], timestamp);
+        let modifiers = modifiers(&keys, &held);
+
+        DeviceSnapshot::from(keys, mouse, modifiers, timestamp)
+    }
+
+    /// Read the click thresholds from the system settings, keeping the default
+    /// long press time
+    pub fn query_click_settings(&self) -> ClickSettings {
+        let mut settings = ClickSettings::default();
+
+        unsafe {
+            settings.multi_click_time = Duration::from_millis(winuser::GetDoubleClickTime() as u64);
+            // The double click rectangle is centered on the first click
+            settings.click_distance = winuser::GetSystemMetrics(winuser::SM_CXDOUBLECLK) / 2;
+        }
+
+        settings
+    }
+
+    /// Read the cursor position into a MouseState, `is_held` telling whether a
+    /// virtual key is held
+    fn mouse_state(&self, is_held: impl Fn(c_int) -> bool, timestamp: Timestamp) -> MouseState {
         // Create the mouse coordinate tuple
         let point: &mut POINT = &mut POINT { x: 0, y: 0 }; // Create a new, empty point to be filled by GetCursorPos()
         let coordinates = if unsafe { winuser::GetCursorPos(point) != 0 } {
@@ -25,63 +388,621 @@ impl DeviceState {
         };
         drop(point); // Drop the point
 
-        // Create the mouse button array
-        let buttons = {
-            let mut buttons = Vec::with_capacity(5); // There will be 5 buttons, so
-                                                     // make a vector with that capacity
-
-            // For each of the five mouse buttons, check whether it's pressed and
-            // push the status to the button vector
-            for button in &[
-                winuser::VK_LBUTTON,
-                winuser::VK_RBUTTON,
-                winuser::VK_MBUTTON,
-                winuser::VK_XBUTTON1,
-                winuser::VK_XBUTTON2,
-            ] {
-                buttons.push(unsafe { winuser::GetAsyncKeyState(*button) as u32 & 0x8000 != 0 });
-            }
+        // GetAsyncKeyState() reports the physical buttons, whatever the system settings
+        let swapped = self.button_mapping == ButtonMapping::Logical
+            && unsafe { winuser::GetSystemMetrics(winuser::SM_SWAPBUTTON) != 0 };
+        let buttons = mouse_buttons(is_held, swapped);
+
+        let mouse = MouseState::from(coordinates, &buttons).with_timestamp(timestamp);
+        match self.monitor_at(coordinates.0, coordinates.1) {
+            Some(monitor) => mouse.with_monitor(monitor),
+            None => mouse,
+        }
+    }
+}
+
+/// Sample whether each of the 256 virtual keys is held, the mouse buttons included
+fn held_virtual_keys() -> Vec<bool> {
+    (0..256)
+        .map(|key| unsafe { winuser::GetAsyncKeyState(key) as u32 & 0x8000 != 0 })
+        .collect()
+}
+
+/// Name the pressed keys of a sample of the virtual keys
+fn pressed_keys(held: &[bool]) -> Vec<KeyCode> {
+    held.iter()
+        .enumerate()
+        .filter(|(_, is_held)| **is_held)
+        .filter_map(|(key, _)| KeyCode::keycode_to_key(key as i32))
+        .collect()
+}
 
-            // Convert a vector of booleans into an array with a length of 5
-            let create_button_arr = |arr: &[bool]| -> [bool; 5] {
-                let mut array = [false; 5];
-                let buttons = &arr[..array.len()]; // Will panic if the vector is shorter than 5 elements
+/// Get the modifiers set by the pressed keys and the Windows keys of a sample of the
+/// virtual keys, along with the engaged lock keys
+fn modifiers(keys: &[KeyCode], held: &[bool]) -> Modifiers {
+    let mut modifiers = Modifiers::from_keys(keys).get_modifiers();
 
-                array.copy_from_slice(buttons);
-                array
+    // KeyCode has no Windows keys
+    if held[winuser::VK_LWIN as usize] || held[winuser::VK_RWIN as usize] {
+        modifiers.push(Modifier::Meta);
+    }
+
+    // Lock keys count while they are toggled on
+    for (modifier, key) in &[
+        (Modifier::CapsLock, winuser::VK_CAPITAL),
+        (Modifier::NumLock, winuser::VK_NUMLOCK),
+    ] {
+        if unsafe { winuser::GetKeyState(*key) as u32 & 0x0001 != 0 } {
+            modifiers.push(*modifier);
+        }
+    }
+
+    Modifiers::new(&modifiers)
+}
+
+/// List the raw input devices
+unsafe fn raw_input_devices() -> Vec<InputDevice> {
+    let size = mem::size_of::<winuser::RAWINPUTDEVICELIST>() as UINT;
+    let mut count = 0;
+    if winuser::GetRawInputDeviceList(ptr::null_mut(), &mut count, size) == UINT::MAX {
+        return Vec::new();
+    }
+
+    let mut list: Vec<winuser::RAWINPUTDEVICELIST> = vec![mem::zeroed(); count as usize];
+    let count = winuser::GetRawInputDeviceList(list.as_mut_ptr(), &mut count, size);
+    if count == UINT::MAX {
+        return Vec::new();
+    }
+    list.truncate(count as usize);
+
+    list.iter()
+        .map(|device| {
+            let kind = match device.dwType {
+                winuser::RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+                winuser::RIM_TYPEMOUSE => DeviceKind::Pointer,
+                _ => hid_kind(device.hDevice),
             };
 
-            create_button_arr(&buttons)
-        };
+            InputDevice::from(
+                device.hDevice as usize as u64,
+                &device_name(device.hDevice),
+                kind,
+                true,
+                false,
+                None,
+            )
+        })
+        .collect()
+}
 
-        MouseState::from(coordinates, buttons)
+/// Compare two lists of devices, removed devices first
+fn device_changes(
+    previous: &[InputDevice],
+    current: Vec<InputDevice>,
+    timestamp: Timestamp,
+) -> Vec<DeviceEvent> {
+    let mut changes: Vec<DeviceEvent> = previous
+        .iter()
+        .filter(|device| !current.iter().any(|other| other.id() == device.id()))
+        .map(|device| DeviceEvent::DeviceRemoved {
+            id: device.id(),
+            timestamp,
+        })
+        .collect();
+    changes.extend(
+        current
+            .into_iter()
+            .filter(|device| !previous.iter().any(|other| other.id() == device.id()))
+            .map(|device| DeviceEvent::DeviceAdded { device, timestamp }),
+    );
+
+    changes
+}
+
+/// Compare the ids of the previous gamepads with the current gamepads, disconnected
+/// gamepads first
+fn gamepad_changes(
+    previous: &[u64],
+    current: &[GamepadState],
+    timestamp: Timestamp,
+) -> Vec<DeviceEvent> {
+    let mut changes: Vec<DeviceEvent> = previous
+        .iter()
+        .filter(|id| !current.iter().any(|gamepad| gamepad.id() == **id))
+        .map(|id| DeviceEvent::GamepadDisconnected { id: *id, timestamp })
+        .collect();
+    changes.extend(
+        current
+            .iter()
+            .filter(|gamepad| !previous.contains(&gamepad.id()))
+            .map(|gamepad| DeviceEvent::GamepadConnected {
+                id: gamepad.id(),
+                name: gamepad.name().to_string(),
+                timestamp,
+            }),
+    );
+
+    changes
+}
+
+/// Get the device path of a raw input device, empty if it cannot be read
+unsafe fn device_name(device: HANDLE) -> String {
+    let mut length = 0;
+    winuser::GetRawInputDeviceInfoW(
+        device,
+        winuser::RIDI_DEVICENAME,
+        ptr::null_mut(),
+        &mut length,
+    );
+
+    let mut name = vec![0u16; length as usize];
+    let copied = winuser::GetRawInputDeviceInfoW(
+        device,
+        winuser::RIDI_DEVICENAME,
+        name.as_mut_ptr() as _,
+        &mut length,
+    );
+    if copied == UINT::MAX {
+        return String::new();
     }
 
-    /// Query the keyboard for all pressed keys, returned as a vector of KeyCodes
-    pub fn query_keymap(&self) -> Vec<KeyCode> {
-        let (mut key_codes, mut key_map) = (Vec::with_capacity(256), Vec::with_capacity(256));
+    let name = &name[..(copied as usize).min(name.len())];
+    String::from_utf16_lossy(name)
+        .trim_end_matches('\0')
+        .to_string()
+}
 
-        for key in 0..256 {
-            key_map.push(unsafe { winuser::GetAsyncKeyState(key) });
-        }
+/// Tell what a HID device other than a keyboard or a mouse is
+unsafe fn hid_kind(device: HANDLE) -> DeviceKind {
+    let mut info: winuser::RID_DEVICE_INFO = mem::zeroed();
+    info.cbSize = mem::size_of::<winuser::RID_DEVICE_INFO>() as UINT;
+    let mut size = info.cbSize;
 
-        for (ix, byte) in key_map.iter().enumerate() {
-            if *byte as u32 & 0x8000 != 0 {
-                // If the keycode is matched, then push the resolved
-                // key to the key_codes vector
-                if let Some(k) = KeyCode::keycode_to_key(ix as i32) {
-                    key_codes.push(k);
-                }
-            }
+    if winuser::GetRawInputDeviceInfoW(
+        device,
+        winuser::RIDI_DEVICEINFO,
+        &mut info as *mut winuser::RID_DEVICE_INFO as _,
+        &mut size,
+    ) == UINT::MAX
+    {
+        return DeviceKind::Other;
+    }
+
+    let hid = info.u.hid();
+    hid_usage_kind(hid.usUsagePage, hid.usUsage)
+}
+
+/// Tell what a HID device is from the usage page and usage of its top-level collection
+fn hid_usage_kind(usage_page: u16, usage: u16) -> DeviceKind {
+    match (usage_page, usage) {
+        // Generic desktop pointer and mouse, digitizer touch pad
+        (0x01, 0x01) | (0x01, 0x02) | (0x0d, 0x05) => DeviceKind::Pointer,
+        // Generic desktop keyboard and keypad
+        (0x01, 0x06) | (0x01, 0x07) => DeviceKind::Keyboard,
+        // Digitizer touch screen
+        (0x0d, 0x04) => DeviceKind::Touch,
+        // Digitizer and pen
+        (0x0d, 0x01) | (0x0d, 0x02) => DeviceKind::Tablet,
+        _ => DeviceKind::Other,
+    }
+}
+
+/// Get the handle of the cursor, `None` when there is no cursor
+fn current_cursor() -> Option<u64> {
+    let mut info: winuser::CURSORINFO = unsafe { mem::zeroed() };
+    info.cbSize = mem::size_of::<winuser::CURSORINFO>() as DWORD;
+
+    if unsafe { winuser::GetCursorInfo(&mut info) } == 0 || info.hCursor.is_null() {
+        return None;
+    }
+    Some(info.hCursor as usize as u64)
+}
+
+/// Get the picture of a cursor, `None` if it cannot be read
+unsafe fn cursor_image(cursor: HCURSOR) -> Option<CursorImage> {
+    let mut info: winuser::ICONINFO = mem::zeroed();
+    if winuser::GetIconInfo(cursor, &mut info) == 0 {
+        return None;
+    }
+
+    let mask = bitmap_pixels(info.hbmMask);
+    let color = if info.hbmColor.is_null() {
+        None
+    } else {
+        bitmap_pixels(info.hbmColor)
+    };
+    wingdi::DeleteObject(info.hbmMask as _);
+    if !info.hbmColor.is_null() {
+        wingdi::DeleteObject(info.hbmColor as _);
+    }
+
+    let (width, mask_height, mask) = mask?;
+    // Monochrome cursors stack their two masks in a single bitmap
+    let height = match &color {
+        Some((_, height, _)) => *height,
+        None => mask_height / 2,
+    };
+    let pixels = cursor_pixels(
+        color.as_ref().map(|(_, _, pixels)| &pixels[..]),
+        &mask,
+        width as usize * height as usize,
+    );
+
+    Some(CursorImage::from(
+        (width, height),
+        (info.xHotspot, info.yHotspot),
+        cursor as usize as u64,
+        "",
+        pixels,
+    ))
+}
+
+/// Get the size and the 32-bit pixels of a bitmap, rows from the top, `None` if it
+/// cannot be read
+unsafe fn bitmap_pixels(bitmap: HBITMAP) -> Option<(u32, u32, Vec<u32>)> {
+    let mut header: wingdi::BITMAP = mem::zeroed();
+    if wingdi::GetObjectW(
+        bitmap as _,
+        mem::size_of::<wingdi::BITMAP>() as c_int,
+        &mut header as *mut wingdi::BITMAP as _,
+    ) == 0
+    {
+        return None;
+    }
+
+    let (width, height) = (header.bmWidth.max(0), header.bmHeight.max(0));
+    let mut info: wingdi::BITMAPINFO = mem::zeroed();
+    info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as DWORD;
+    info.bmiHeader.biWidth = width;
+    // A negative height asks for the rows from the top
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+    let mut pixels = vec![0u32; width as usize * height as usize];
+    let dc = winuser::GetDC(ptr::null_mut());
+    let rows = wingdi::GetDIBits(
+        dc,
+        bitmap,
+        0,
+        height as u32,
+        pixels.as_mut_ptr() as _,
+        &mut info,
+        wingdi::DIB_RGB_COLORS,
+    );
+    winuser::ReleaseDC(ptr::null_mut(), dc);
+
+    Some((width as u32, height as u32, pixels)).filter(|_| rows != 0)
+}
+
+/// Combine the bitmaps of a cursor into premultiplied ARGB pixels. Cursors without
+/// alpha are transparent where their AND mask is set. Monochrome cursors have no
+/// color bitmap and a mask holding the AND mask above the XOR mask, where pixels
+/// that invert the screen are drawn black.
+fn cursor_pixels(color: Option<&[u32]>, mask: &[u32], count: usize) -> Vec<u32> {
+    const OPAQUE: u32 = 0xff00_0000;
+    let is_set = |pixel: Option<&u32>| matches!(pixel, Some(pixel) if pixel & 0x00ff_ffff != 0);
+
+    match color {
+        Some(color) if color.iter().any(|pixel| pixel & OPAQUE != 0) => {
+            color.iter().map(|pixel| premultiply(*pixel)).collect()
         }
+        Some(color) => (0..count)
+            .map(|i| {
+                if is_set(mask.get(i)) {
+                    0
+                } else {
+                    color.get(i).map_or(OPAQUE, |pixel| pixel | OPAQUE)
+                }
+            })
+            .collect(),
+        None => (0..count)
+            .map(
+                |i| match (is_set(mask.get(i)), is_set(mask.get(count + i))) {
+                    (true, false) => 0,
+                    (false, true) => 0xffff_ffff,
+                    _ => OPAQUE,
+                },
+            )
+            .collect(),
+    }
+}
+
+/// Multiply the color channels of an ARGB pixel with straight alpha by its alpha
+fn premultiply(pixel: u32) -> u32 {
+    let alpha = pixel >> 24;
+    let channel = |shift: u32| (((pixel >> shift & 0xff) * alpha + 127) / 255) << shift;
+
+    alpha << 24 | channel(16) | channel(8) | channel(0)
+}
+
+/// Read the state of the XInput controller with this user index
+fn gamepad_state(index: DWORD, gamepad: &xinput::XINPUT_GAMEPAD) -> GamepadState {
+    const BUTTONS: [(WORD, GamepadButton); 10] = [
+        (xinput::XINPUT_GAMEPAD_A, GamepadButton::South),
+        (xinput::XINPUT_GAMEPAD_B, GamepadButton::East),
+        (xinput::XINPUT_GAMEPAD_Y, GamepadButton::North),
+        (xinput::XINPUT_GAMEPAD_X, GamepadButton::West),
+        (
+            xinput::XINPUT_GAMEPAD_LEFT_SHOULDER,
+            GamepadButton::LeftBumper,
+        ),
+        (
+            xinput::XINPUT_GAMEPAD_RIGHT_SHOULDER,
+            GamepadButton::RightBumper,
+        ),
+        (xinput::XINPUT_GAMEPAD_BACK, GamepadButton::Select),
+        (xinput::XINPUT_GAMEPAD_START, GamepadButton::Start),
+        (xinput::XINPUT_GAMEPAD_LEFT_THUMB, GamepadButton::LeftStick),
+        (
+            xinput::XINPUT_GAMEPAD_RIGHT_THUMB,
+            GamepadButton::RightStick,
+        ),
+    ];
 
-        key_codes
+    let held = |mask: WORD| gamepad.wButtons & mask != 0;
+    let mut buttons: Vec<GamepadButton> = BUTTONS
+        .iter()
+        .filter(|(mask, _)| held(*mask))
+        .map(|(_, button)| *button)
+        .collect();
+    let threshold = xinput::XINPUT_GAMEPAD_TRIGGER_THRESHOLD;
+    if gamepad.bLeftTrigger > threshold {
+        buttons.push(GamepadButton::LeftTrigger);
     }
+    if gamepad.bRightTrigger > threshold {
+        buttons.push(GamepadButton::RightTrigger);
+    }
+
+    let direction = |negative, positive| i8::from(held(positive)) - i8::from(held(negative));
+    let dpad = (
+        direction(
+            xinput::XINPUT_GAMEPAD_DPAD_LEFT,
+            xinput::XINPUT_GAMEPAD_DPAD_RIGHT,
+        ),
+        direction(
+            xinput::XINPUT_GAMEPAD_DPAD_UP,
+            xinput::XINPUT_GAMEPAD_DPAD_DOWN,
+        ),
+    );
+
+    // XInput sticks point upwards for positive y values
+    let left_deadzone = xinput::XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE;
+    let right_deadzone = xinput::XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE;
+    let left_stick = (
+        stick_value(gamepad.sThumbLX, left_deadzone),
+        -stick_value(gamepad.sThumbLY, left_deadzone),
+    );
+    let right_stick = (
+        stick_value(gamepad.sThumbRX, right_deadzone),
+        -stick_value(gamepad.sThumbRY, right_deadzone),
+    );
+
+    GamepadState::from(
+        u64::from(index),
+        &format!("XInput controller {}", index + 1),
+        &buttons,
+    )
+    .with_sticks(left_stick, right_stick)
+    .with_triggers(
+        f64::from(gamepad.bLeftTrigger) / 255.0,
+        f64::from(gamepad.bRightTrigger) / 255.0,
+    )
+    .with_dpad(dpad)
+}
+
+/// Scale a stick axis to -1.0..=1.0, 0.0 within the dead zone around its center
+fn stick_value(value: i16, deadzone: i16) -> f64 {
+    if value.unsigned_abs() <= deadzone.unsigned_abs() {
+        return 0.0;
+    }
+    (f64::from(value) / 32767.0).clamp(-1.0, 1.0)
+}
+
+/// Get the identifier of the foreground window, `None` if no window has the focus
+fn foreground_window() -> Option<u64> {
+    let window = unsafe { winuser::GetForegroundWindow() };
+
+    Some(window as usize as u64).filter(|_| !window.is_null())
+}
+
+/// Describe a window with its title, class and process id, `None` if it does not exist
+unsafe fn window_info(window: HWND) -> Option<FocusedWindow> {
+    if winuser::IsWindow(window) == 0 {
+        return None;
+    }
+
+    let mut title = vec![0; winuser::GetWindowTextLengthW(window).max(0) as usize + 1];
+    let length = winuser::GetWindowTextW(window, title.as_mut_ptr(), title.len() as c_int);
+    let title = String::from_utf16_lossy(&title[..length.max(0) as usize]);
+
+    // Class names are at most 256 characters long
+    let mut class = [0; 257];
+    let length = winuser::GetClassNameW(window, class.as_mut_ptr(), class.len() as c_int);
+    let class = String::from_utf16_lossy(&class[..length.max(0) as usize]);
+
+    let mut pid: DWORD = 0;
+    winuser::GetWindowThreadProcessId(window, &mut pid);
+
+    Some(FocusedWindow::from(
+        window as usize as u64,
+        &title,
+        &class,
+        Some(pid).filter(|pid| *pid != 0),
+    ))
+}
+
+/// Add a monitor to the `Vec<HMONITOR>` that `monitors` points to, as the callback
+/// of `EnumDisplayMonitors()`
+unsafe extern "system" fn push_monitor(
+    monitor: HMONITOR,
+    _hdc: HDC,
+    _rect: LPRECT,
+    monitors: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(monitors as *mut Vec<HMONITOR>);
+    monitors.push(monitor);
+
+    TRUE
+}
+
+/// Name the held mouse buttons, `is_held` telling whether a virtual key is held
+fn mouse_buttons(is_held: impl Fn(c_int) -> bool, swapped: bool) -> Vec<MouseButton> {
+    let (primary, secondary) = if swapped {
+        (winuser::VK_RBUTTON, winuser::VK_LBUTTON)
+    } else {
+        (winuser::VK_LBUTTON, winuser::VK_RBUTTON)
+    };
+
+    [
+        (primary, MouseButton::Left),
+        (secondary, MouseButton::Right),
+        (winuser::VK_MBUTTON, MouseButton::Middle),
+        (winuser::VK_XBUTTON1, MouseButton::Back),
+        (winuser::VK_XBUTTON2, MouseButton::Forward),
+    ]
+    .iter()
+    .filter(|(key, _)| is_held(*key))
+    .map(|(_, button)| *button)
+    .collect()
 }
 
 impl Default for DeviceState {
     /// Create a new DeviceState
     fn default() -> Self {
-        Self::new()
+        Self::new().expect("Failed to create DeviceState")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_changes_test() {
+        let timestamp = Timestamp::now();
+        let device = |id| InputDevice::from(id, "", DeviceKind::Keyboard, true, false, None);
+
+        assert_eq!(
+            device_changes(
+                &[device(1), device(2)],
+                vec![device(2), device(3)],
+                timestamp
+            ),
+            vec![
+                DeviceEvent::DeviceRemoved { id: 1, timestamp },
+                DeviceEvent::DeviceAdded {
+                    device: device(3),
+                    timestamp
+                },
+            ]
+        );
+        assert!(device_changes(&[device(1)], vec![device(1)], timestamp).is_empty());
+
+        let gamepad = |id| GamepadState::from(id, "Pad", &[]);
+        assert_eq!(
+            gamepad_changes(&[0, 1], &[gamepad(1), gamepad(3)], timestamp),
+            vec![
+                DeviceEvent::GamepadDisconnected { id: 0, timestamp },
+                DeviceEvent::GamepadConnected {
+                    id: 3,
+                    name: "Pad".to_string(),
+                    timestamp
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hid_usage_kind_test() {
+        assert_eq!(hid_usage_kind(0x01, 0x06), DeviceKind::Keyboard);
+        assert_eq!(hid_usage_kind(0x0d, 0x04), DeviceKind::Touch);
+        assert_eq!(hid_usage_kind(0x0d, 0x02), DeviceKind::Tablet);
+        assert_eq!(hid_usage_kind(0x0d, 0x05), DeviceKind::Pointer);
+        // A gamepad
+        assert_eq!(hid_usage_kind(0x01, 0x05), DeviceKind::Other);
+    }
+
+    #[test]
+    fn cursor_pixels_test() {
+        const WHITE: u32 = 0x00ff_ffff;
+
+        // An alpha cursor is premultiplied
+        assert_eq!(
+            cursor_pixels(
+                Some(&[0xff40_2010, 0x80ff_ffff, 0x8040_2010, 0]),
+                &[0; 4],
+                4
+            ),
+            vec![0xff40_2010, 0x8080_8080, 0x8020_1008, 0]
+        );
+
+        // A color cursor without alpha is masked
+        assert_eq!(
+            cursor_pixels(Some(&[0x0012_3456, 0x0012_3456]), &[0, WHITE], 2),
+            vec![0xff12_3456, 0]
+        );
+
+        // Monochrome: black, white, transparent and inverted
+        let mask = [0, 0, WHITE, WHITE, 0, WHITE, 0, WHITE];
+        assert_eq!(
+            cursor_pixels(None, &mask, 4),
+            vec![0xff00_0000, 0xffff_ffff, 0, 0xff00_0000]
+        );
+    }
+
+    #[test]
+    fn gamepad_state_test() {
+        let mut gamepad: xinput::XINPUT_GAMEPAD = unsafe { mem::zeroed() };
+        gamepad.wButtons = xinput::XINPUT_GAMEPAD_A
+            | xinput::XINPUT_GAMEPAD_START
+            | xinput::XINPUT_GAMEPAD_DPAD_UP
+            | xinput::XINPUT_GAMEPAD_DPAD_RIGHT;
+        gamepad.bRightTrigger = 255;
+        gamepad.sThumbLX = 1000;
+        gamepad.sThumbLY = 32767;
+
+        let state = gamepad_state(1, &gamepad);
+        assert_eq!(state.name(), "XInput controller 2");
+        assert_eq!(
+            state.buttons(),
+            &[
+                GamepadButton::South,
+                GamepadButton::RightTrigger,
+                GamepadButton::Start
+            ]
+        );
+        assert_eq!(state.dpad(), (1, -1));
+        assert_eq!(state.left_stick(), (0.0, -1.0));
+        assert_eq!(state.right_trigger(), 1.0);
+    }
+
+    #[test]
+    fn mouse_buttons_test() {
+        let held = |keys: &'static [c_int]| move |key| keys.contains(&key);
+
+        assert_eq!(
+            mouse_buttons(held(&[winuser::VK_LBUTTON, winuser::VK_XBUTTON1]), false),
+            vec![MouseButton::Left, MouseButton::Back]
+        );
+        assert_eq!(
+            mouse_buttons(held(&[winuser::VK_RBUTTON, winuser::VK_XBUTTON2]), false),
+            vec![MouseButton::Right, MouseButton::Forward]
+        );
+        assert_eq!(
+            mouse_buttons(held(&[winuser::VK_MBUTTON]), false),
+            vec![MouseButton::Middle]
+        );
+
+        // Left-handed: the right button is the primary one
+        assert_eq!(
+            mouse_buttons(held(&[winuser::VK_RBUTTON]), true),
+            vec![MouseButton::Left]
+        );
+        assert_eq!(
+            mouse_buttons(held(&[winuser::VK_LBUTTON]), true),
+            vec![MouseButton::Right]
+        );
     }
 }
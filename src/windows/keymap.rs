@@ -82,6 +82,12 @@ pub enum KeyCode {
     Insert,
     Tab,
 
+    // Arrow keys
+    Up,
+    Down,
+    Left,
+    Right,
+
     // Numberpad keys
     Numlock,
     Numpad0,
@@ -151,6 +157,12 @@ impl KeyCode {
             winuser::VK_INSERT => Some(KeyCode::Insert),
             winuser::VK_TAB => Some(KeyCode::Tab),
 
+            // Arrow keys
+            winuser::VK_UP => Some(KeyCode::Up),
+            winuser::VK_DOWN => Some(KeyCode::Down),
+            winuser::VK_LEFT => Some(KeyCode::Left),
+            winuser::VK_RIGHT => Some(KeyCode::Right),
+
             // Numberpad keys
             winuser::VK_NUMLOCK => Some(KeyCode::Numlock),
             winuser::VK_NUMPAD0 => Some(KeyCode::Numpad0),
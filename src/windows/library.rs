@@ -0,0 +1,52 @@
+//! Loading the system libraries that older Windows versions lack at runtime, so that
+//! a missing library disables a feature instead of preventing the program from starting
+
+use std::{
+    ffi::{CString, OsStr},
+    mem,
+    os::windows::ffi::OsStrExt,
+};
+use winapi::{shared::minwindef::HMODULE, um::libloaderapi};
+
+/// A dynamic-link library, freed when dropped
+pub(super) struct Library {
+    handle: HMODULE,
+}
+
+// Module handles are valid in every thread of the process
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    /// Open the first library of `names` that can be found, such as `["XInput1_4.dll", "XInput1_3.dll"]`
+    pub(super) fn open(names: &[&str]) -> Option<Library> {
+        names.iter().find_map(|name| {
+            let name: Vec<u16> = OsStr::new(name).encode_wide().chain(Some(0)).collect();
+            let handle = unsafe { libloaderapi::LoadLibraryW(name.as_ptr()) };
+
+            Some(Library { handle }).filter(|library| !library.handle.is_null())
+        })
+    }
+
+    /// Look a function up. `T` must be the `unsafe extern "system" fn` type of the symbol.
+    pub(super) unsafe fn function<T: Copy>(&self, name: &str) -> Option<T> {
+        assert_eq!(mem::size_of::<T>(), mem::size_of::<usize>());
+
+        let name = CString::new(name).ok()?;
+        let symbol = libloaderapi::GetProcAddress(self.handle, name.as_ptr());
+
+        if symbol.is_null() {
+            None
+        } else {
+            Some(mem::transmute_copy(&symbol))
+        }
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            libloaderapi::FreeLibrary(self.handle);
+        }
+    }
+}
@@ -2,6 +2,9 @@
 
 mod devicestate;
 mod keymap;
+mod library;
+mod shcore;
+mod xinput;
 
 pub use devicestate::DeviceState;
 pub use keymap::KeyCode;
@@ -0,0 +1,64 @@
+//! Monitor DPI from the shell scaling library, whose functions are loaded at runtime
+//! since they first shipped with Windows 8.1
+
+use super::library::Library;
+use winapi::{
+    shared::{
+        minwindef::UINT,
+        windef::HMONITOR,
+        winerror::{HRESULT, S_OK},
+    },
+    um::shellscalingapi::{MDT_EFFECTIVE_DPI, MDT_RAW_DPI, MONITOR_DPI_TYPE},
+};
+
+type GetDpiForMonitor =
+    unsafe extern "system" fn(HMONITOR, MONITOR_DPI_TYPE, *mut UINT, *mut UINT) -> HRESULT;
+
+/// The DPI a monitor has when nothing is scaled
+const BASE_DPI: f64 = 96.0;
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+/// The shell scaling functions, available from Windows 8.1
+pub(super) struct ShellScaling {
+    get_dpi_for_monitor: GetDpiForMonitor,
+    _library: Library,
+}
+
+impl ShellScaling {
+    /// Load shcore.dll, `None` before Windows 8.1
+    pub(super) fn load() -> Option<ShellScaling> {
+        let library = Library::open(&["shcore.dll"])?;
+
+        Some(ShellScaling {
+            get_dpi_for_monitor: unsafe { library.function("GetDpiForMonitor")? },
+            _library: library,
+        })
+    }
+
+    /// Get the horizontal and vertical DPI of a monitor, `None` if it cannot be told
+    pub(super) fn dpi(&self, monitor: HMONITOR, kind: MONITOR_DPI_TYPE) -> Option<(u32, u32)> {
+        let (mut dpi_x, mut dpi_y) = (0, 0);
+        let result = unsafe { (self.get_dpi_for_monitor)(monitor, kind, &mut dpi_x, &mut dpi_y) };
+
+        Some((dpi_x, dpi_y)).filter(|_| result == S_OK)
+    }
+
+    /// Get the scale the user chose for a monitor, 1.0 if it cannot be told
+    pub(super) fn scale(&self, monitor: HMONITOR) -> f64 {
+        self.dpi(monitor, MDT_EFFECTIVE_DPI)
+            .map_or(1.0, |(dpi_x, _)| f64::from(dpi_x) / BASE_DPI)
+    }
+
+    /// Get the physical width and height of a monitor of this size in pixels, in
+    /// millimeters, from the DPI its EDID reports, `None` if it cannot be told
+    pub(super) fn physical_size(&self, monitor: HMONITOR, size: (u32, u32)) -> Option<(u32, u32)> {
+        let (dpi_x, dpi_y) = self
+            .dpi(monitor, MDT_RAW_DPI)
+            .filter(|(dpi_x, dpi_y)| *dpi_x > 0 && *dpi_y > 0)?;
+        let millimeters = |pixels: u32, dpi: u32| {
+            (f64::from(pixels) / f64::from(dpi) * MILLIMETERS_PER_INCH).round() as u32
+        };
+
+        Some((millimeters(size.0, dpi_x), millimeters(size.1, dpi_y)))
+    }
+}
@@ -0,0 +1,39 @@
+//! Xbox-style controllers from XInput, whose library is loaded at runtime since
+//! its version depends on the Windows release and may be missing altogether
+
+use super::library::Library;
+use std::mem;
+use winapi::{
+    shared::{minwindef::DWORD, winerror::ERROR_SUCCESS},
+    um::xinput,
+};
+
+type GetState = unsafe extern "system" fn(DWORD, *mut xinput::XINPUT_STATE) -> DWORD;
+
+/// The XInput functions, available when any version of the library is installed
+pub(super) struct XInput {
+    get_state: GetState,
+    _library: Library,
+}
+
+impl XInput {
+    /// Load the most recent XInput library, `None` if none is installed
+    pub(super) fn load() -> Option<XInput> {
+        let library = Library::open(&["XInput1_4.dll", "XInput1_3.dll", "XInput9_1_0.dll"])?;
+
+        Some(XInput {
+            get_state: unsafe { library.function("XInputGetState")? },
+            _library: library,
+        })
+    }
+
+    /// Get the state of the controller with this user index, `None` if it is not connected
+    pub(super) fn state(&self, index: DWORD) -> Option<xinput::XINPUT_STATE> {
+        let mut state: xinput::XINPUT_STATE = unsafe { mem::zeroed() };
+        if unsafe { (self.get_state)(index, &mut state) } != ERROR_SUCCESS {
+            return None;
+        }
+
+        Some(state)
+    }
+}